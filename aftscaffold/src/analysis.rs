@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use gridmap::Point;
+use gridrobot::{Heading, Orientation};
+
+use crate::Grid;
+
+// Scaffold cells where a straight run ends
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Junction {
+    // At most one scaffold neighbor
+    ENDPOINT,
+    // Two neighbors at right angles
+    CORNER,
+    // Three or four neighbors
+    INTERSECTION,
+}
+
+// A straight run of scaffold between two junctions
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Segment {
+    pub from: Point,
+    pub to: Point,
+    pub heading: Heading,
+    // Moves from one end to the other
+    pub length: usize,
+}
+
+// The scaffold as a graph with junctions for nodes and segments for edges
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ScaffoldGraph {
+    pub junctions: BTreeMap<Point, Junction>,
+    pub segments: Vec<Segment>,
+}
+impl ScaffoldGraph {
+    pub fn from_grid(grid: &Grid) -> Self {
+        let junctions: BTreeMap<Point, Junction> = grid.cells.points()
+            .filter_map(|p| grid.junction(p).map(|j| (p, j)))
+            .collect();
+        let mut segments = Vec::new();
+        for from in junctions.keys() {
+            for heading in Heading::ALL.iter() {
+                let mut to = *from;
+                let mut length = 0;
+                loop {
+                    let next = heading.step_from(to, Orientation::SCREEN);
+                    if !grid.loc_is_valid(next) {
+                        break;
+                    }
+                    to = next;
+                    length += 1;
+                    if junctions.contains_key(&to) {
+                        break;
+                    }
+                }
+                // Each segment is found from both ends; keep one
+                if length > 0 && *from < to {
+                    segments.push(Segment{ from: *from, to, heading: *heading, length });
+                }
+            }
+        }
+        ScaffoldGraph{ junctions, segments }
+    }
+    fn of_kind(&self, kind: Junction) -> Vec<Point> {
+        self.junctions.iter().filter(|(_, j)| **j == kind).map(|(p, _)| *p).collect()
+    }
+    pub fn endpoints(&self) -> Vec<Point> {
+        self.of_kind(Junction::ENDPOINT)
+    }
+    pub fn corners(&self) -> Vec<Point> {
+        self.of_kind(Junction::CORNER)
+    }
+    pub fn intersections(&self) -> Vec<Point> {
+        self.of_kind(Junction::INTERSECTION)
+    }
+    // Segments meeting at `p`
+    pub fn segments_at(&self, p: Point) -> Vec<&Segment> {
+        self.segments.iter().filter(|s| s.from == p || s.to == p).collect()
+    }
+    pub fn total_length(&self) -> usize {
+        self.segments.iter().map(|s| s.length).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Junction, ScaffoldGraph};
+    use crate::Grid;
+    use gridmap::Point;
+    use gridrobot::Heading;
+
+    #[test]
+    fn finds_segments() {
+        let grid = Grid::from_text(&b"..#....\n..#....\n#######\n..#...#\n..#####\n"[..]).unwrap();
+        let graph = ScaffoldGraph::from_grid(&grid);
+        assert_eq!(vec![Point::new(0, 2), Point::new(2, 0)], graph.endpoints());
+        assert_eq!(vec![Point::new(2, 4), Point::new(6, 2), Point::new(6, 4)], graph.corners());
+        assert_eq!(vec![Point::new(2, 2)], graph.intersections());
+        assert_eq!(Some(&Junction::INTERSECTION), graph.junctions.get(&Point::new(2, 2)));
+        assert_eq!(6, graph.segments.len());
+        assert_eq!(4, graph.segments_at(Point::new(2, 2)).len());
+        // Every scaffold link counted once
+        assert_eq!(16, graph.total_length());
+        let top = graph.segments_at(Point::new(2, 0))[0];
+        assert_eq!((Point::new(2, 2), Heading::DOWN, 2), (top.to, top.heading, top.length));
+    }
+}
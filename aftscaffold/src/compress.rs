@@ -0,0 +1,214 @@
+use std::fmt;
+
+use crate::Instruction;
+
+// Breaks straights into single steps, so a function can end partway along
+// one and the next pick up the rest
+fn steps(path: &[Instruction]) -> Vec<Instruction> {
+    path.iter()
+        .flat_map(|inst| match inst {
+            Instruction::STRAIGHT(n) => vec![Instruction::STRAIGHT(1); *n as usize],
+            turn => vec![*turn],
+        })
+        .collect()
+}
+
+// Joins runs of single steps back into straights
+fn join_steps(steps: &[Instruction]) -> Vec<Instruction> {
+    let mut out: Vec<Instruction> = Vec::new();
+    for inst in steps {
+        match (out.last_mut(), inst) {
+            (Some(Instruction::STRAIGHT(n)), Instruction::STRAIGHT(m)) => *n += m,
+            _ => out.push(*inst),
+        }
+    }
+    out
+}
+
+fn line(insts: &[Instruction]) -> String {
+    insts.iter()
+        .map(|inst| String::from_utf8(inst.to_ascii()).unwrap())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn function_name(i: usize) -> char {
+    (b'A' + i as u8) as char
+}
+
+// A main routine calling movement functions by index
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Routine {
+    pub main: Vec<usize>,
+    pub functions: Vec<Vec<Instruction>>,
+}
+impl Routine {
+    pub fn main_line(&self) -> String {
+        self.main.iter()
+            .map(|f| function_name(*f).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+    pub fn function_lines(&self) -> Vec<String> {
+        self.functions.iter().map(|f| line(f)).collect()
+    }
+    // The path the robot follows running the routine
+    pub fn expand(&self) -> Vec<Instruction> {
+        let calls: Vec<Instruction> = self.main.iter()
+            .flat_map(|f| self.functions[*f].iter().cloned())
+            .collect();
+        join_steps(&calls)
+    }
+    // Input for the robot's ASCII program, ending with the answer to
+    // whether it should show a continuous video feed
+    pub fn to_ascii(&self, video: bool) -> Vec<isize> {
+        let mut lines = vec![self.main_line()];
+        lines.extend(self.function_lines());
+        lines.push(if video { "y" } else { "n" }.to_string());
+        lines.iter()
+            .flat_map(|l| l.bytes().map(|b| b as isize).chain(Some(10)))
+            .collect()
+    }
+}
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Main: {}", self.main_line())?;
+        for (i, l) in self.function_lines().iter().enumerate() {
+            writeln!(f, "{}: {}", function_name(i), l)?;
+        }
+        Ok(())
+    }
+}
+
+// Factors a path into a main routine and up to `functions` movement
+// functions, each line no longer than `line_limit` characters counting
+// commas but not the newline
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Compressor {
+    functions: usize,
+    line_limit: usize,
+}
+impl Compressor {
+    pub fn new(functions: usize, line_limit: usize) -> Self {
+        assert!(functions <= 26, "precondition failed, functions are named A to Z");
+        Compressor{ functions, line_limit }
+    }
+    // Calls that fit on the main routine's line
+    fn max_calls(&self) -> usize {
+        self.line_limit.div_ceil(2)
+    }
+    fn fits(&self, steps: &[Instruction]) -> bool {
+        line(&join_steps(steps)).len() <= self.line_limit
+    }
+    // Every routine that expands to `path`, with functions numbered in order
+    // of first call. Unused functions are left empty.
+    pub fn compress(&self, path: &[Instruction]) -> Vec<Routine> {
+        let steps = steps(path);
+        let mut out = Vec::new();
+        self.search(&steps, 0, &mut Vec::new(), &mut Vec::new(), &mut out, usize::MAX);
+        out
+    }
+    // The first routine found, without looking for others
+    pub fn first(&self, path: &[Instruction]) -> Option<Routine> {
+        let steps = steps(path);
+        let mut out = Vec::new();
+        self.search(&steps, 0, &mut Vec::new(), &mut Vec::new(), &mut out, 1);
+        out.pop()
+    }
+    // The routine with the fewest calls
+    pub fn best(&self, path: &[Instruction]) -> Option<Routine> {
+        self.compress(path).into_iter().min_by_key(|r| r.main.len())
+    }
+    fn search(&self, steps: &[Instruction], pos: usize, main: &mut Vec<usize>,
+            funcs: &mut Vec<Vec<Instruction>>, out: &mut Vec<Routine>, limit: usize) {
+        if out.len() == limit {
+            return;
+        }
+        if pos == steps.len() {
+            let mut functions: Vec<Vec<Instruction>> = funcs.iter().map(|f| join_steps(f)).collect();
+            functions.resize(self.functions, Vec::new());
+            out.push(Routine{ main: main.clone(), functions });
+            return;
+        }
+        if main.len() == self.max_calls() {
+            return;
+        }
+        for i in 0..funcs.len() {
+            if steps[pos..].starts_with(&funcs[i]) {
+                main.push(i);
+                self.search(steps, pos + funcs[i].len(), main, funcs, out, limit);
+                main.pop();
+            }
+        }
+        if funcs.len() == self.functions {
+            return;
+        }
+        // Lines only get longer as the function does
+        for end in (pos + 1)..=steps.len() {
+            let f = &steps[pos..end];
+            if !self.fits(f) {
+                break;
+            }
+            if funcs.iter().any(|g| g[..] == *f) {
+                continue;
+            }
+            funcs.push(f.to_vec());
+            main.push(funcs.len() - 1);
+            self.search(steps, end, main, funcs, out, limit);
+            main.pop();
+            funcs.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compressor, Routine};
+    use crate::Instruction::{self, LEFT, RIGHT, STRAIGHT};
+
+    fn path(s: &str) -> Vec<Instruction> {
+        s.split(',')
+            .map(|t| match t {
+                "L" => LEFT,
+                "R" => RIGHT,
+                n => STRAIGHT(n.parse().unwrap()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compresses_example() {
+        let p = path("R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+        let routines = Compressor::new(3, 20).compress(&p);
+        assert!(!routines.is_empty());
+        for r in routines.iter() {
+            assert_eq!(p, r.expand());
+            assert!(r.main_line().len() <= 20);
+            assert!(r.function_lines().iter().all(|l| l.len() <= 20));
+        }
+        assert!(routines.iter().any(|r| r.main_line() == "A,B,C,B,A,C"
+            && r.function_lines() == vec!["R,8,R,8", "R,4,R,4,R,8", "L,6,L,2"]));
+        // Long functions cover the path in three calls
+        assert_eq!(3, Compressor::new(3, 20).best(&p).unwrap().main.len());
+    }
+
+    #[test]
+    fn splits_long_straights() {
+        // R,12 is too long for a line of 3, but R,6 then 6 is not
+        let p = path("R,12");
+        let routines = Compressor::new(2, 3).compress(&p);
+        assert!(routines.iter().all(|r| r.expand() == p));
+        assert!(routines.iter().any(|r| r.function_lines() == vec!["R,6", "6"]));
+        assert!(Compressor::new(1, 3).compress(&p).is_empty());
+        assert_eq!(Some(&routines[0]), Compressor::new(2, 3).first(&p).as_ref());
+    }
+
+    #[test]
+    fn robot_input() {
+        let r = Routine{ main: vec![0, 1, 0], functions: vec![path("L,10"), path("R,4"), vec![]] };
+        let ascii: String = r.to_ascii(false).iter().map(|c| *c as u8 as char).collect();
+        assert_eq!("A,B,A\nL,10\nR,4\n\nn\n", ascii);
+        assert_eq!(path("L,10,R,4,L,10"), r.expand());
+        assert_eq!("Main: A,B,A\nA: L,10\nB: R,4\nC: \n", r.to_string());
+    }
+}
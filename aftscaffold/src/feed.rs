@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use gridmap::Point;
+use gridrobot::Heading;
+
+use crate::{Cell, Grid};
+
+// One picture from the camera. `robot` is None once the robot has fallen
+// off the scaffold, which the camera shows as an X.
+#[derive(Clone)]
+pub struct Frame {
+    pub grid: Grid,
+    pub robot: Option<(Point, Heading)>,
+}
+impl Frame {
+    // None unless every line is camera output
+    pub fn parse(lines: &[String]) -> Option<Self> {
+        let mut robot = None;
+        let mut rows = Vec::new();
+        for (y, line) in lines.iter().enumerate() {
+            let mut row = Vec::new();
+            for (x, ch) in line.chars().enumerate() {
+                row.push(match ch {
+                    '.' => Cell::SPACE,
+                    '#' | 'X' => Cell::SCAFFOLD,
+                    _ => {
+                        let heading = Heading::from_char(ch)?;
+                        robot = Some((Point::from((x, y)), heading));
+                        Cell::SCAFFOLD
+                    },
+                });
+            }
+            rows.push(row);
+        }
+        let cells = gridmap::Grid::from_rows(rows).ok()?;
+        Some(Frame{ grid: Grid::new(cells), robot })
+    }
+}
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.grid.cells.rows().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                match self.robot {
+                    Some((p, h)) if p == Point::from((x, y)) => write!(f, "{}", h.to_char())?,
+                    _ => write!(f, "{}", c)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Splits the robot's output into frames as it arrives. Frames end with a
+// blank line; blocks of prompts in between are skipped, and a value too
+// big to be a character is the dust collected.
+#[derive(Default)]
+pub struct FeedParser {
+    line: String,
+    lines: Vec<String>,
+    dust: Option<usize>,
+}
+impl FeedParser {
+    pub fn new() -> Self {
+        FeedParser::default()
+    }
+    // Returns a frame when `code` completes one
+    pub fn push(&mut self, code: isize) -> Option<Frame> {
+        if code > 127 {
+            self.dust = Some(code as usize);
+            return None;
+        }
+        if code != 10 {
+            self.line.push(code as u8 as char);
+            return None;
+        }
+        if !self.line.is_empty() {
+            self.lines.push(std::mem::take(&mut self.line));
+            return None;
+        }
+        let lines = std::mem::take(&mut self.lines);
+        if lines.is_empty() { None } else { Frame::parse(&lines) }
+    }
+    pub fn dust(&self) -> Option<usize> {
+        self.dust
+    }
+}
+
+// The whole of a video feed
+pub struct Feed {
+    pub frames: Vec<Frame>,
+    pub dust: Option<usize>,
+}
+impl Feed {
+    pub fn parse(output: &[isize]) -> Self {
+        let mut parser = FeedParser::new();
+        let frames = output.iter().filter_map(|code| parser.push(*code)).collect();
+        Feed{ frames, dust: parser.dust() }
+    }
+    // Where the robot was in each frame it could be seen in
+    pub fn track(&self) -> Vec<(Point, Heading)> {
+        self.frames.iter().filter_map(|f| f.robot).collect()
+    }
+    pub fn fell_off(&self) -> bool {
+        self.frames.iter().any(|f| f.robot.is_none())
+    }
+    // Whether the robot was seen on every bit of scaffold in the first frame
+    pub fn covers_scaffold(&self) -> bool {
+        let visited: HashSet<Point> = self.track().iter().map(|(p, _)| *p).collect();
+        match self.frames.first() {
+            Some(first) => first.grid.cells.points()
+                .filter(|p| first.grid.loc_is_valid(*p))
+                .all(|p| visited.contains(&p)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feed, FeedParser};
+    use gridmap::Point;
+    use gridrobot::Heading;
+
+    fn codes(s: &str) -> Vec<isize> {
+        s.bytes().map(|b| b as isize).collect()
+    }
+
+    #[test]
+    fn parses_frames() {
+        let mut out = codes("#>#\n...\n\nMain:\nFunction A:\n\n##v\n..#\n\n###\n..X\n\n");
+        out.push(1234);
+        let feed = Feed::parse(&out);
+        assert_eq!(3, feed.frames.len());
+        assert_eq!(Some(1234), feed.dust);
+        assert_eq!(vec![(Point::new(1, 0), Heading::RIGHT), (Point::new(2, 0), Heading::DOWN)],
+            feed.track());
+        assert!(feed.fell_off());
+        assert!(!feed.covers_scaffold());
+        assert_eq!("##v\n..#\n", feed.frames[1].to_string());
+    }
+
+    #[test]
+    fn frames_arrive_as_completed() {
+        let mut parser = FeedParser::new();
+        let frames: Vec<usize> = codes("^#\n\n#^\n\n").iter().enumerate()
+            .filter_map(|(i, c)| parser.push(*c).map(|_| i))
+            .collect();
+        assert_eq!(vec![3, 7], frames);
+        assert_eq!(None, parser.dust());
+    }
+}
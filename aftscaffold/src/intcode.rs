@@ -16,6 +16,7 @@ pub struct Resources {
     input: VecDeque<isize>,
     output: VecDeque<isize>,
     relative_base: isize,
+    pause_on_output: bool,
 }
 impl Resources {
     pub fn new(mut mem: Vec<isize>) -> Resources {
@@ -27,8 +28,14 @@ impl Resources {
             input: VecDeque::new(),
             output: VecDeque::new(),
             relative_base: 0,
+            pause_on_output: false,
         }
     }
+    // Suspends after every output so the caller can read it while the
+    // program is still going; `resume` carries on from the next instruction
+    pub fn set_pause_on_output(&mut self, pause: bool) {
+        self.pause_on_output = pause;
+    }
     pub fn relative_base(&self) -> isize {
         self.relative_base
     }
@@ -266,6 +273,9 @@ impl Processor {
             let inst = self.insts.get(&opcode)
                 .expect("instruction not found");
             inst.execute(res);
+            if opcode == 4 && res.pause_on_output && res.get_status() == Status::RUNNING {
+                res.set_status(Status::SUSPENDED);
+            }
         }
     }
 
@@ -343,4 +353,22 @@ mod tests {
         processor.execute(&mut res);
         assert_eq!(1125899906842624, res.read_output().unwrap());
     }
+
+    #[test]
+    fn pause_on_output() {
+        use super::Status;
+        let processor = Processor::new_intcode();
+        let mut res = Resources::new(vec![104,1,104,2,3,9,4,9,99,0]);
+        res.set_pause_on_output(true);
+        res.write_input(3);
+        processor.execute(&mut res);
+        assert_eq!((Status::SUSPENDED, Some(1), None),
+            (res.get_status(), res.read_output(), res.read_output()));
+        processor.resume(&mut res);
+        assert_eq!(Some(2), res.read_output());
+        processor.resume(&mut res);
+        assert_eq!(Some(3), res.read_output());
+        processor.resume(&mut res);
+        assert_eq!((Status::TERMINATED, None), (res.get_status(), res.read_output()));
+    }
 }
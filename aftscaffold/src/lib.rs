@@ -1,134 +1,98 @@
-use std::cmp::min;
 use std::fmt;
+use std::io::BufRead;
+
+use gridmap::{GridError, Point};
+use gridrobot::{Heading, Turn};
 
 mod intcode;
 use intcode::{Processor, Resources, Status};
 
-#[derive(Debug)]
-enum Movement {
-    STRAIGHT,
-    LEFT,
-    RIGHT,
-}
+mod compress;
+pub use compress::{Compressor, Routine};
 
-#[derive(PartialEq, Copy, Clone)]
-enum Direction {
-    LEFT,
-    UP,
-    RIGHT,
-    DOWN,
-}
-impl Direction {
-    pub fn after_movement(&self, m: Movement) -> Direction {
-        match m {
-            Movement::LEFT => match self {
-                Direction::LEFT => Direction::DOWN,
-                Direction::UP => Direction::LEFT,
-                Direction::RIGHT => Direction::UP,
-                Direction::DOWN => Direction::RIGHT,
-            },
-            Movement::RIGHT => match self {
-                Direction::LEFT => Direction::UP,
-                Direction::UP => Direction::RIGHT,
-                Direction::RIGHT => Direction::DOWN,
-                Direction::DOWN => Direction::LEFT,
-            },
-            Movement::STRAIGHT => *self,
-        }
+mod paths;
+use paths::PathEnumerator;
+
+mod feed;
+pub use feed::{Feed, FeedParser, Frame};
+
+mod analysis;
+pub use analysis::{Junction, ScaffoldGraph, Segment};
+
+// What the camera shows, as text
+fn camera_text(mem: Vec<isize>) -> String {
+    let proc = Processor::new_intcode();
+    let mut res = Resources::new(mem);
+    proc.execute(&mut res);
+    let mut text = String::new();
+    while let Some(code) = res.read_output() {
+        text.push(code as u8 as char);
     }
+    text
 }
 
-struct Robot {
-    x: usize,
-    y: usize,
-    d: Direction,
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Robot {
+    bot: gridrobot::Robot,
 }
 impl Robot {
+    pub fn new(pos: Point, heading: Heading) -> Self {
+        Robot{ bot: gridrobot::Robot::new(pos, heading) }
+    }
+    // The first robot symbol in the camera picture
+    pub fn from_text<R: BufRead>(data: R) -> Option<Self> {
+        data.lines().enumerate().find_map(|(y, line)| {
+            line.ok()?.chars().enumerate().find_map(|(x, ch)| {
+                Heading::from_char(ch).map(|h| Robot::new(Point::from((x, y)), h))
+            })
+        })
+    }
     pub fn from_intcode(mem: Vec<isize>) -> Option<Self> {
-        let proc = Processor::new_intcode();
-        let mut res = Resources::new(mem);
-        proc.execute(&mut res);
-        let (mut x, mut y): (usize, usize) = (0, 0);
-        loop {
-            if let Some(code) = res.read_output() {
-                if code == 10 {
-                    x = 0;
-                    y += 1;
-                }
-                match code as u8 as char {
-                    '<' => return Some(Robot{x: x, y: y, d: Direction::LEFT}),
-                    '^' => return Some(Robot{x: x, y: y, d: Direction::UP}),
-                    '>' => return Some(Robot{x: x, y: y, d: Direction::RIGHT}),
-                    'v' => return Some(Robot{x: x, y: y, d: Direction::DOWN}),
-                    _ => {},
-                }
-                if code != 10 {
-                    x += 1;
+        Robot::from_text(camera_text(mem).as_bytes())
+    }
+    pub fn pos(&self) -> Point {
+        self.bot.pos()
+    }
+    pub fn heading(&self) -> Heading {
+        self.bot.heading()
+    }
+    // Prefers going straight, then left, then right
+    pub fn advance(&mut self, grid: &Grid) -> Option<Turn> {
+        for &t in [Turn::STRAIGHT, Turn::LEFT, Turn::RIGHT].iter() {
+            if let Some(loc) = self.bot.ahead(t) {
+                if grid.loc_is_valid(loc) {
+                    self.bot.turn_and_forward(t);
+                    return Some(t);
                 }
-            } else {
-                break;
             }
         }
         None
     }
-    pub fn loc_after_movement(&self, m: Movement) -> (isize, isize) {
-        let (x, y) = (self.x as isize, self.y as isize);
-        match self.d.after_movement(m) {
-            Direction::LEFT => (x-1, y),
-            Direction::UP => (x, y-1),
-            Direction::RIGHT => (x+1, y),
-            Direction::DOWN => (x, y+1),
-        }
-    }
-    pub fn advance(&mut self, grid: &Grid) -> Option<Movement> {
-        let loc_straight_ahead = self.loc_after_movement(Movement::STRAIGHT);
-        let loc_leftward = self.loc_after_movement(Movement::LEFT);
-        let loc_rightward = self.loc_after_movement(Movement::RIGHT);
-        if grid.loc_is_valid(loc_straight_ahead) {
-            self.x = loc_straight_ahead.0 as usize;
-            self.y = loc_straight_ahead.1 as usize;
-            self.d = self.d.after_movement(Movement::STRAIGHT);
-            Some(Movement::STRAIGHT)
-        } else if grid.loc_is_valid(loc_leftward) {
-            self.x = loc_leftward.0 as usize;
-            self.y = loc_leftward.1 as usize;
-            self.d = self.d.after_movement(Movement::LEFT);
-            Some(Movement::LEFT)
-        } else if grid.loc_is_valid(loc_rightward) {
-            self.x = loc_rightward.0 as usize;
-            self.y = loc_rightward.1 as usize;
-            self.d = self.d.after_movement(Movement::RIGHT);
-            Some(Movement::RIGHT)
-        } else {
-            None
-        }
-    }
 }
 impl fmt::Display for Robot {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ch = match self.d {
-            Direction::LEFT => '<',
-            Direction::UP => '^',
-            Direction::RIGHT => '>',
-            Direction::DOWN => 'v',
-        };
-        write!(f, "{}", ch)
+        write!(f, "{}", self.bot)
     }
 }
 
-#[derive(PartialEq)]
-enum Cell {
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Cell {
     SPACE,
     SCAFFOLD,
 }
 impl Cell {
-    pub fn from_code(code: isize) -> Self {
-        match code as u8 as char {
-            '.' => Cell::SPACE,
-            '#' | '<' | '^' | '>' | 'v' => Cell::SCAFFOLD,
-            _ => panic!("invalid input code: {}", code),
+    // The robot always stands on scaffold
+    pub fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '.' => Some(Cell::SPACE),
+            '#' | '<' | '^' | '>' | 'v' => Some(Cell::SCAFFOLD),
+            _ => None,
         }
     }
+    pub fn from_code(code: isize) -> Self {
+        Cell::from_char(code as u8 as char)
+            .unwrap_or_else(|| panic!("invalid input code: {}", code))
+    }
 }
 impl fmt::Display for Cell {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -140,110 +104,99 @@ impl fmt::Display for Cell {
     }
 }
 
-struct Grid {
-    cells: Vec<Vec<Cell>>,
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Grid {
+    cells: gridmap::Grid<Cell>,
 }
 impl Grid {
+    pub fn new(cells: gridmap::Grid<Cell>) -> Self {
+        Grid{ cells }
+    }
+    pub fn from_text<R: BufRead>(data: R) -> Result<Self, GridError> {
+        gridmap::Grid::parse(data, Cell::from_char).map(Grid::new)
+    }
     pub fn from_intcode(mem: Vec<isize>) -> Self {
-        let proc = Processor::new_intcode();
-        let mut res = Resources::new(mem);
-        proc.execute(&mut res);
-        let mut cells = Vec::new();
-        let mut row = Vec::new();
-        loop {
-            if let Some(code) = res.read_output() {
-                if code == 10 {
-                    if row.len() > 0 {
-                        cells.push(row);
-                        row = Vec::new();
-                    }
-                } else {
-                    row.push(Cell::from_code(code));
-                }
-            } else {
-                break;
-            }
-        }
-        Grid{ cells: cells }
+        Grid::from_text(camera_text(mem).as_bytes()).expect("bad camera picture")
+    }
+    pub fn cells(&self) -> &gridmap::Grid<Cell> {
+        &self.cells
     }
     pub fn intersections(&self) -> Vec<(usize, usize)> {
-        let mut isects = Vec::new();
-        for y in 1..self.height()-1 {
-            for x in 1..self.width()-1 {
-                if self.cells[y][x] == Cell::SCAFFOLD &&
-                    self.cells[y-1][x] == Cell::SCAFFOLD &&
-                    self.cells[y+1][x] == Cell::SCAFFOLD &&
-                    self.cells[y][x-1] == Cell::SCAFFOLD &&
-                    self.cells[y][x+1] == Cell::SCAFFOLD {
-                        isects.push((x, y));
-                }
-            }
-        }
-        isects
+        self.cells.iter()
+            .filter(|(p, c)| **c == Cell::SCAFFOLD &&
+                self.cells.neighbors4(*p).filter(|n| self.loc_is_valid(*n)).count() == 4)
+            .map(|(p, _)| (p.x as usize, p.y as usize))
+            .collect()
     }
-    pub fn width(&self) -> usize {
-        self.cells[0].len()
+    pub fn loc_is_valid(&self, loc: Point) -> bool {
+        self.cells.get(loc) == Some(&Cell::SCAFFOLD)
     }
-    pub fn height(&self) -> usize {
-        self.cells.len()
+    // Where a scaffold cell stops being part of a straight run, if it does
+    pub fn junction(&self, p: Point) -> Option<Junction> {
+        if !self.loc_is_valid(p) {
+            return None;
+        }
+        let links: Vec<Point> = self.cells.neighbors4(p).filter(|n| self.loc_is_valid(*n)).collect();
+        match links.len() {
+            0 | 1 => Some(Junction::ENDPOINT),
+            2 if links[0].x != links[1].x && links[0].y != links[1].y => Some(Junction::CORNER),
+            2 => None,
+            _ => Some(Junction::INTERSECTION),
+        }
     }
-    pub fn loc_is_valid(&self, loc: (isize, isize)) -> bool {
-        loc.0 >= 0 && (loc.0 as usize) < self.width() &&
-            loc.1 >= 0 && (loc.1 as usize) < self.height() &&
-            self.cells[loc.1 as usize][loc.0 as usize] == Cell::SCAFFOLD
+    pub fn analyze(&self) -> ScaffoldGraph {
+        ScaffoldGraph::from_grid(self)
     }
 }
 impl fmt::Display for Grid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for r in self.cells.iter() {
-            for c in r.iter() {
-                write!(f, "{}", c)?
-            }
-            write!(f, "\n")?
-        }
-        Ok(())
+        write!(f, "{}", self.cells)
     }
 }
 
-#[derive(Debug)]
-enum Instruction {
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Instruction {
     LEFT,
     RIGHT,
     STRAIGHT(i64),
 }
+impl Instruction {
+    pub fn to_ascii(&self) -> Vec<u8> {
+        match self {
+            Instruction::LEFT => vec![b'L'],
+            Instruction::RIGHT => vec![b'R'],
+            Instruction::STRAIGHT(n) => n.to_string().into_bytes(),
+        }
+    }
+}
 
 pub fn intersection_alignment_sum(mem: &Vec<isize>) -> usize {
     let grid = Grid::from_intcode(mem.clone());
-    print!("grid:\n{}", grid);
     grid.intersections().iter().map(|(x, y)| x*y).sum()
 }
 
-fn generate_instruction_list(mem: &Vec<isize>) -> Vec<Instruction> {
-    let mut robot = Robot::from_intcode(mem.clone()).expect("no robot found");
-    let grid = Grid::from_intcode(mem.clone());
+// Straights count the cells moved onto, turns included
+fn instructions_from_turns(turns: &[Turn]) -> Vec<Instruction> {
     let mut movecount = 0;
     let mut instruction_list = Vec::new();
-    loop {
-        if let Some(m) = robot.advance(&grid) {
-            match m {
-                Movement::LEFT => {
-                    if movecount > 0 {
-                        instruction_list.push(Instruction::STRAIGHT(movecount));
-                    }
-                    instruction_list.push(Instruction::LEFT);
-                    movecount = 1;
-                },
-                Movement::RIGHT => {
-                    if movecount > 0 {
-                        instruction_list.push(Instruction::STRAIGHT(movecount));
-                    }
-                    instruction_list.push(Instruction::RIGHT);
-                    movecount = 1;
-                },
-                Movement::STRAIGHT => movecount += 1,
-            }
-        } else {
-            break;
+    for m in turns {
+        match m {
+            Turn::LEFT => {
+                if movecount > 0 {
+                    instruction_list.push(Instruction::STRAIGHT(movecount));
+                }
+                instruction_list.push(Instruction::LEFT);
+                movecount = 1;
+            },
+            Turn::RIGHT => {
+                if movecount > 0 {
+                    instruction_list.push(Instruction::STRAIGHT(movecount));
+                }
+                instruction_list.push(Instruction::RIGHT);
+                movecount = 1;
+            },
+            Turn::STRAIGHT => movecount += 1,
+            Turn::AROUND => panic!("robot never turns around"),
         }
     }
     if movecount > 0 {
@@ -252,122 +205,88 @@ fn generate_instruction_list(mem: &Vec<isize>) -> Vec<Instruction> {
     instruction_list
 }
 
-fn abbreviate_instruction_list(insts: &Vec<Instruction>) -> Vec<i64> {
-    let mut out = Vec::new();
-    for i in 0..(insts.len()/2) {
-        let mut n = 0;
-        if let Instruction::STRAIGHT(x) = insts[i*2+1] {
-            n = x;
-        }
-        if let Instruction::LEFT = insts[i*2] {
-            n = -n;
-        }
-        out.push(n);
+fn generate_instruction_list(robot: &Robot, grid: &Grid) -> Vec<Instruction> {
+    let mut robot = robot.clone();
+    let mut turns = Vec::new();
+    while let Some(m) = robot.advance(grid) {
+        turns.push(m);
     }
-    out
+    instructions_from_turns(&turns)
 }
 
-fn replace_subsequences(s: &[i64], sub: &[i64], rep: &[i64]) -> Vec<i64> {
-    let mut out = Vec::new();
-    let mut i = 0;
-    while i < s.len() {
-        if sub[..] == s[i..min(s.len(), i+sub.len())] {
-            out.extend(rep);
-            i += sub.len();
-        } else {
-            out.push(s[i]);
-            i += 1;
-        }
-    }
-    out
-}
-
-fn find_suitable_subsequences_helper(s: &Vec<i64>, so_far: Vec<Vec<i64>>) -> Option<Vec<Vec<i64>>> {
-    if so_far.len() == 3 {
-        if s.is_empty() {
-            Some(so_far)
-        } else {
-            None
-        }
-    } else {
-        for i in (1..min(6, s.len())).rev() {
-            let subseq = &s[0..i];
-            let new_s = replace_subsequences(&s, subseq, &vec![]);
-            let mut new_so_far = so_far.clone();
-            new_so_far.push(subseq.to_vec());
-            if let Some(seqs) = find_suitable_subsequences_helper(&new_s, new_so_far) {
-                return Some(seqs);
-            }
-        }
-        None
+// Compresses the greedy path if it fits, and otherwise tries the other
+// ways over the scaffold until one does
+fn find_routine(mem: &Vec<isize>, compressor: &Compressor) -> Option<Routine> {
+    let robot = Robot::from_intcode(mem.clone()).expect("no robot found");
+    let grid = Grid::from_intcode(mem.clone());
+    let greedy = generate_instruction_list(&robot, &grid);
+    if let Some(routine) = compressor.first(&greedy) {
+        return Some(routine);
     }
+    let mut found = None;
+    let mut tried = 0;
+    PathEnumerator::new(&grid).for_each(&robot, |path| {
+        tried += 1;
+        found = compressor.first(path);
+        found.is_none()
+    });
+    println!("greedy path does not compress, tried {} others", tried);
+    found
 }
 
-fn find_suitable_subsequences(s: &[i64]) -> Option<Vec<Vec<i64>>> {
-    find_suitable_subsequences_helper(&s.to_vec(), Vec::new())
+pub fn movement_routine(mem: &Vec<isize>) -> Option<Routine> {
+    find_routine(mem, &Compressor::new(3, 20))
 }
 
-fn add_commas_and_newline(s: &[i64]) -> Vec<i64> {
-    let mut out = Vec::new();
-    for i in 0..s.len() {
-        out.push(s[i]);
-        if i < s.len() - 1 {
-            out.push(',' as i64);
-        } else {
-            out.push(10);
-        }
+// Runs the routine with the video feed on, handing each frame to
+// `on_frame` as it comes out of the robot, while the program is paused
+// after the frame's last character. Returns the dust collected.
+pub fn watch_routine<F: FnMut(&Frame)>(mem: &Vec<isize>, routine: &Routine, mut on_frame: F) -> Option<usize> {
+    let proc = Processor::new_intcode();
+    let mut res = Resources::new(mem.clone());
+    res.write_mem(0, 2);
+    res.set_pause_on_output(true);
+    for x in routine.to_ascii(true) {
+        res.write_input(x);
     }
-    out
-}
-
-fn abbreviated_sequence_to_output(s: &[i64]) -> Vec<i64> {
-    let mut out = Vec::new();
-    for i in 0..s.len() {
-        if s[i] < 0 {
-            out.push('L' as i64);
-            out.push(',' as i64);
-        } else {
-            out.push('R' as i64);
-            out.push(',' as i64);
+    let mut parser = FeedParser::new();
+    proc.execute(&mut res);
+    loop {
+        let mut output = false;
+        while let Some(code) = res.read_output() {
+            output = true;
+            if let Some(frame) = parser.push(code) {
+                on_frame(&frame);
+            }
         }
-        out.extend(s[i].abs().to_string().chars().map(|d| d as i64));
-        if i < s.len() - 1 {
-            out.push(',' as i64);
-        } else {
-            out.push(10);
+        // Suspended with nothing to show means it wants more input than
+        // the routine gave it
+        if res.get_status() != Status::SUSPENDED || !output {
+            break;
         }
+        proc.resume(&mut res);
     }
-    out
+    parser.dust()
+}
+
+pub fn record_feed(mem: &Vec<isize>, routine: &Routine) -> Feed {
+    let mut frames = Vec::new();
+    let dust = watch_routine(mem, routine, |frame| frames.push(frame.clone()));
+    Feed{ frames, dust }
 }
 
 pub fn total_space_dust(mem: &Vec<isize>) -> usize {
-    let insts = generate_instruction_list(mem);
-    let abbr_insts = abbreviate_instruction_list(&insts);
-    let subseqs = find_suitable_subsequences(&abbr_insts)
-        .expect("no suitable subsequence breakdown found");
-    let mut main_seq = abbr_insts.clone();
-    main_seq = replace_subsequences(&main_seq, &subseqs[0], &vec!['A' as i64]);
-    main_seq = replace_subsequences(&main_seq, &subseqs[1], &vec!['B' as i64]);
-    main_seq = replace_subsequences(&main_seq, &subseqs[2], &vec!['C' as i64]);
-    main_seq = add_commas_and_newline(&main_seq);
-    println!("main: {:?}, funcs: {:?}", main_seq, subseqs);
+    let routine = find_routine(mem, &Compressor::new(3, 20))
+        .expect("no suitable routine found");
+    println!("{}", routine);
 
     let proc = Processor::new_intcode();
     let mut res = Resources::new(mem.clone());
     res.write_mem(0, 2);
-    for x in main_seq.iter() {
-        res.write_input(*x as isize);
-        println!("sent to input: {}", *x);
-    }
-    for ss in subseqs.iter() {
-        let routine = abbreviated_sequence_to_output(ss);
-        for x in routine.iter() {
-            res.write_input(*x as isize);
-            println!("sent to input: {}", *x);
-        }
+    for x in routine.to_ascii(false) {
+        res.write_input(x);
+        println!("sent to input: {}", x);
     }
-    res.write_input('n' as isize);
-    res.write_input(10 as isize);
     proc.execute(&mut res);
     assert_eq!(Status::TERMINATED, res.get_status());
     let mut total = 0;
@@ -392,42 +311,40 @@ mod tests {
     }
 
     #[test]
-    fn check_replace_subsequences() {
-        use super::replace_subsequences;
-        assert_eq!(vec![4, 5, 2, 3],
-            replace_subsequences(
-                &vec![1, 2, 3, 4, 5, 1, 2, 3, 2, 3], &vec![1, 2, 3], &vec![]));
-        assert_eq!(vec![65, 4, 5, 65, 2, 3],
-            replace_subsequences(
-                &vec![1, 2, 3, 4, 5, 1, 2, 3, 2, 3], &vec![1, 2, 3], &vec![65]));
-    }
-
-    #[test]
-    fn check_find_suitable_subsequences() {
-        use super::find_suitable_subsequences;
-        assert_eq!(vec![vec![1, 2, 3, 4, 5], vec![1], vec![2, 3]],
-            find_suitable_subsequences(&vec![1, 2, 3, 4, 5, 1, 2, 3, 2, 3]).unwrap());
-        assert_eq!(vec![
-                vec![-4, 8, -6, -10],
-                vec![-6, 8, 10, -6, -6],
-                vec![-4, -4, -10],
-            ],
-            find_suitable_subsequences(&vec![
-                -4, 8, -6, -10, -6, 8, 10, -6, -6, -4, 8, -6, -10, -6, 8, 10,
-                -6, -6, -4, -4, -10, -4, -4, -10, -6, 8, 10, -6, -6, -4, 8, -6,
-                -10, -6, 8, 10, -6, -6, -4, -4, -10
-            ]).unwrap());
+    fn robot_follows_scaffold() {
+        use super::{Cell, Grid, Robot, Turn};
+        use gridmap::Point;
+        use gridrobot::Heading;
+        let rows = ["#####", "#...#", "^...#"];
+        let grid = Grid{ cells: gridmap::Grid::from_rows(rows.iter()
+            .map(|r| r.chars().map(|c| Cell::from_code(c as isize)).collect())
+            .collect()).unwrap() };
+        let mut robot = Robot{ bot: gridrobot::Robot::new(Point::new(0, 2), Heading::UP) };
+        let mut moves = Vec::new();
+        while let Some(m) = robot.advance(&grid) {
+            moves.push(m);
+        }
+        assert_eq!(8, moves.len());
+        assert_eq!(Turn::RIGHT, moves[2]);
+        assert_eq!(Turn::RIGHT, moves[6]);
+        assert_eq!(Point::new(4, 2), robot.bot.pos());
+        assert_eq!("v", format!("{}", robot));
     }
 
     #[test]
-    fn check_abbreviated_sequence_to_output() {
-        use super::abbreviated_sequence_to_output;
-        assert_eq!(vec![
-                'L' as i64, ',' as i64, '4' as i64, ',' as i64,
-                'R' as i64, ',' as i64, '8' as i64, ',' as i64,
-                'L' as i64, ',' as i64, '6' as i64, ',' as i64,
-                'L' as i64, ',' as i64, '1' as i64, '0' as i64, 10,
-            ],
-            abbreviated_sequence_to_output(&vec![-4, 8, -6, -10]));
+    fn check_compressed_example() {
+        use super::{Compressor, Instruction};
+        use super::Instruction::{LEFT, RIGHT, STRAIGHT};
+        let abbr = vec![
+            -4, 8, -6, -10, -6, 8, 10, -6, -6, -4, 8, -6, -10, -6, 8, 10,
+            -6, -6, -4, -4, -10, -4, -4, -10, -6, 8, 10, -6, -6, -4, 8, -6,
+            -10, -6, 8, 10, -6, -6, -4, -4, -10
+        ];
+        let path: Vec<Instruction> = abbr.iter()
+            .flat_map(|n: &i64| vec![if *n < 0 { LEFT } else { RIGHT }, STRAIGHT(n.abs())])
+            .collect();
+        let routine = Compressor::new(3, 20).best(&path).unwrap();
+        assert_eq!(path, routine.expand());
+        assert_eq!("L,4,R,8,L,6,L,10", routine.function_lines()[0]);
     }
 }
@@ -1,7 +1,7 @@
 use std::io;
 use std::fs::read_to_string;
 
-use aftscaffold::{intersection_alignment_sum, total_space_dust};
+use aftscaffold::{intersection_alignment_sum, Grid, movement_routine, record_feed, total_space_dust};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -9,6 +9,13 @@ fn main() -> io::Result<()> {
         .map(|x| x.parse::<isize>().expect("failed to parse input"))
         .collect();
 
+    let grid = Grid::from_intcode(mem.clone());
+    print!("grid:\n{}", grid);
+    let graph = grid.analyze();
+    println!("{} endpoints, {} corners, {} intersections, {} segments, {} moves of scaffold",
+        graph.endpoints().len(), graph.corners().len(), graph.intersections().len(),
+        graph.segments.len(), graph.total_length());
+
     // Part 1
     let ialign_sum = intersection_alignment_sum(&mem);
     println!("intersection alignment sum: {}", ialign_sum);
@@ -17,5 +24,14 @@ fn main() -> io::Result<()> {
     let total = total_space_dust(&mem);
     println!("total space dust: {}", total);
 
+    // Same routine again, watched through the video feed
+    let routine = movement_routine(&mem).expect("no suitable routine found");
+    let feed = record_feed(&mem, &routine);
+    if let Some(last) = feed.frames.last() {
+        print!("{}", last);
+    }
+    println!("{} frames, robot seen on all scaffold: {}, fell off: {}, dust: {:?}",
+        feed.frames.len(), feed.covers_scaffold(), feed.fell_off(), feed.dust);
+
     Ok(())
 }
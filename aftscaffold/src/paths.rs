@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use gridmap::Point;
+use gridrobot::Turn;
+
+use crate::{instructions_from_turns, Grid, Instruction, Robot};
+
+type Edge = (Point, Point);
+
+fn edge(a: Point, b: Point) -> Edge {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+// Walks of the robot that cross every stretch of scaffold between two
+// neighboring cells exactly once. Only intersections offer a choice: go
+// straight over, or turn onto the crossing scaffold.
+pub(crate) struct PathEnumerator<'a> {
+    grid: &'a Grid,
+    edges: usize,
+}
+impl<'a> PathEnumerator<'a> {
+    pub fn new(grid: &'a Grid) -> Self {
+        let edges = grid.cells.points()
+            .filter(|p| grid.loc_is_valid(*p))
+            .map(|p| [p.offset(1, 0), p.offset(0, 1)].iter().filter(|n| grid.loc_is_valid(**n)).count())
+            .sum();
+        PathEnumerator{ grid, edges }
+    }
+    // Calls `visit` with each path from the robot's position until it
+    // returns false. Returns whether every path was visited.
+    pub fn for_each<F: FnMut(&[Instruction]) -> bool>(&self, robot: &Robot, mut visit: F) -> bool {
+        self.walk(robot.bot.clone(), &mut HashSet::new(), &mut Vec::new(), &mut visit)
+    }
+    fn walk<F: FnMut(&[Instruction]) -> bool>(&self, bot: gridrobot::Robot, used: &mut HashSet<Edge>,
+            turns: &mut Vec<Turn>, visit: &mut F) -> bool {
+        let mut moved = false;
+        for &t in [Turn::STRAIGHT, Turn::LEFT, Turn::RIGHT].iter() {
+            let next = match bot.ahead(t) {
+                Some(next) if self.grid.loc_is_valid(next) => next,
+                _ => continue,
+            };
+            let e = edge(bot.pos(), next);
+            if used.contains(&e) {
+                continue;
+            }
+            moved = true;
+            let mut bot = bot.clone();
+            bot.turn_and_forward(t);
+            used.insert(e);
+            turns.push(t);
+            let go_on = self.walk(bot, used, turns, visit);
+            turns.pop();
+            used.remove(&e);
+            if !go_on {
+                return false;
+            }
+        }
+        if !moved && used.len() == self.edges {
+            return visit(&instructions_from_turns(turns));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathEnumerator;
+    use crate::{instructions_from_turns, Grid, Robot};
+
+    #[test]
+    fn enumerates_turns_at_intersections() {
+        let picture = "..v....\n..#....\n#######\n..#...#\n..#####\n";
+        let g = Grid::from_text(picture.as_bytes()).unwrap();
+        let robot = Robot::from_text(picture.as_bytes()).unwrap();
+        let mut paths = Vec::new();
+        assert!(PathEnumerator::new(&g).for_each(&robot, |p| {
+            paths.push(p.to_vec());
+            true
+        }));
+        assert_eq!(2, paths.len());
+        // The greedy robot's path is one of them
+        let mut greedy = robot.clone();
+        let mut turns = Vec::new();
+        while let Some(t) = greedy.advance(&g) {
+            turns.push(t);
+        }
+        assert!(paths.contains(&instructions_from_turns(&turns)));
+
+        let mut count = 0;
+        assert!(!PathEnumerator::new(&g).for_each(&robot, |_| {
+            count += 1;
+            false
+        }));
+        assert_eq!(1, count);
+    }
+}
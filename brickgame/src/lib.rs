@@ -1,5 +1,8 @@
 use std::fmt;
 
+use gridmap::{Grid, Point};
+use rasterimg::{Palette, Raster, Rgb};
+
 mod intcode;
 use intcode::{Processor, Resources};
 
@@ -22,6 +25,26 @@ impl Tile {
             _ => panic!("invalid tile"),
         }
     }
+    pub fn raster_value(&self) -> u32 {
+        match self {
+            Tile::EMPTY => 0,
+            Tile::WALL => 1,
+            Tile::BLOCK => 2,
+            Tile::PADDLE => 3,
+            Tile::BALL => 4,
+        }
+    }
+}
+
+// Colors indexed by `Tile::raster_value`
+pub fn tile_palette() -> Palette {
+    Palette::new(vec![
+        Rgb::new(0, 0, 0),
+        Rgb::new(128, 128, 128),
+        Rgb::new(200, 60, 40),
+        Rgb::new(230, 230, 230),
+        Rgb::new(250, 210, 40),
+    ])
 }
 impl fmt::Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -40,13 +63,13 @@ const DIM_X: usize = 50;
 const DIM_Y: usize = 30;
 #[derive(Clone)]
 struct Screen {
-    tiles: [[Tile; DIM_X]; DIM_Y],
+    tiles: Grid<Tile>,
     score: isize,
 }
 impl Screen {
     pub fn new() -> Self {
         Screen{
-            tiles: [[Tile::EMPTY; DIM_X]; DIM_Y],
+            tiles: Grid::new(DIM_X, DIM_Y, Tile::EMPTY),
             score: 0,
         }
     }
@@ -54,26 +77,22 @@ impl Screen {
         if x == -1 && y == 0 {
             self.score = t;
         } else {
-            let (xp, yp) = (x as usize, y as usize);
-            let tile = Tile::new(t);
-            self.tiles[yp][xp] = tile;
+            self.tiles[Point::new(x as i32, y as i32)] = Tile::new(t);
         }
     }
     pub fn num_block_tiles(&self) -> i32 {
-        self.tiles.iter().map(
-            |row| row.iter().filter(|t| **t == Tile::BLOCK).count()).sum::<usize>() as i32
+        self.tiles.iter().filter(|(_, t)| **t == Tile::BLOCK).count() as i32
+    }
+    // Cropped to the tiles the game has actually drawn
+    pub fn to_raster(&self) -> Raster {
+        let px = self.tiles.iter().map(|(_, t)| t.raster_value()).collect();
+        Raster::from_vec(px, DIM_X, DIM_Y).crop_to_content(Tile::EMPTY.raster_value())
     }
 }
 impl fmt::Display for Screen {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Score: {}\n", self.score)?;
-        for y in 0..DIM_Y {
-            for x in 0..DIM_X {
-                write!(f, "{}", self.tiles[y][x])?;
-            }
-            write!(f, "\n")?;
-        }
-        Ok(())
+        writeln!(f, "Score: {}", self.score)?;
+        write!(f, "{}", self.tiles)
     }
 }
 
@@ -136,6 +155,9 @@ impl Arcade {
     pub fn score(&self) -> isize {
         self.res.read_mem(386)
     }
+    pub fn to_raster(&self) -> Raster {
+        self.screen.to_raster()
+    }
 }
 impl fmt::Display for Arcade {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -149,4 +171,18 @@ mod tests {
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn screen_to_raster() {
+        use super::Screen;
+        let mut screen = Screen::new();
+        screen.set_tile(2, 1, 1);
+        screen.set_tile(4, 2, 4);
+        screen.set_tile(-1, 0, 1000);
+        let raster = screen.to_raster();
+        assert_eq!((3, 2), (raster.width(), raster.height()));
+        assert_eq!(&[1,0,0, 0,0,4], raster.pixels());
+        assert_eq!(1000, screen.score);
+        assert!(format!("{}", screen).starts_with("Score: 1000\n"));
+    }
 }
@@ -1,8 +1,9 @@
 use std::{char, io};
 use std::fs::read_to_string;
 
-use brickgame::Arcade;
+use brickgame::{tile_palette, Arcade};
 use getch::Getch;
+use rasterimg::{ExportOptions, Format};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -32,6 +33,9 @@ fn main() -> io::Result<()> {
                 'l' => arcade.joystick_input(1),
                 's' => arcade.save(),
                 'r' => arcade.load(),
+                'p' => arcade.to_raster().save(
+                    &ExportOptions::new(Format::PNG).palette(tile_palette()).scale(8),
+                    "screen.png")?,
                 _ => {},
             };
         }
@@ -0,0 +1,518 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{Config, Network, NAT};
+
+const MAGIC: &str = "cat6cap 1";
+const FAULTS: &str = "faults";
+
+// A packet as routed in round `tick`. Packets the NAT sends have it as
+// their source.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Packet {
+    pub tick: usize,
+    pub src: isize,
+    pub dst: isize,
+    pub x: isize,
+    pub y: isize,
+}
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {} {}", self.tick, self.src, self.dst, self.x, self.y)
+    }
+}
+
+// A copy of a packet reaching its destination in round `tick`. Later than
+// the packet's own round means it was held up, and arrived at the start of
+// the round before any node ran.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Arrival {
+    pub tick: usize,
+    pub packet: Packet,
+}
+impl Arrival {
+    pub fn is_delayed(&self) -> bool {
+        self.tick > self.packet.tick
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum CaptureError {
+    HEADER,
+    PACKET { line: usize, text: String },
+    // Packets out of the order a network routes them in. Arrivals are
+    // counted after the packets.
+    ORDER { index: usize },
+    // A packet from an address with no node or NAT
+    SOURCE { index: usize, src: isize },
+    IO(String),
+}
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::HEADER => write!(f, "not a capture, expected {:?} first", MAGIC),
+            CaptureError::PACKET{ line, text } => write!(f,
+                "line {} is not a packet: {:?}", line, text),
+            CaptureError::ORDER{ index } => write!(f, "packet {} is out of order", index),
+            CaptureError::SOURCE{ index, src } => write!(f,
+                "packet {} comes from {}, which has no node", index, src),
+            CaptureError::IO(msg) => write!(f, "read failed: {}", msg),
+        }
+    }
+}
+impl error::Error for CaptureError {}
+
+// Packets in the order they were sent. With faults on, what actually
+// arrived is kept too, since packets can be lost, copied or held up on
+// the way. Saved as text: a header line, a "faults" line if arrivals are
+// kept, one "tick src dst x y" line per packet and one "@ tick packet"
+// line per arrival.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Capture {
+    packets: Vec<Packet>,
+    arrivals: Option<Vec<Arrival>>,
+}
+impl Capture {
+    pub fn new() -> Self {
+        Capture::default()
+    }
+    pub fn push(&mut self, p: Packet) {
+        self.packets.push(p);
+    }
+    pub fn packets(&self) -> &[Packet] {
+        &self.packets
+    }
+    // Keeps arrivals from now on
+    pub fn track_arrivals(&mut self) {
+        if self.arrivals.is_none() {
+            self.arrivals = Some(Vec::new());
+        }
+    }
+    // Ignored unless arrivals are tracked
+    pub fn push_arrival(&mut self, a: Arrival) {
+        if let Some(arrivals) = self.arrivals.as_mut() {
+            arrivals.push(a);
+        }
+    }
+    // None if every packet arrived once, as soon as it was sent
+    pub fn arrivals(&self) -> Option<&[Arrival]> {
+        self.arrivals.as_deref()
+    }
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", MAGIC)?;
+        if self.arrivals.is_some() {
+            writeln!(out, "{}", FAULTS)?;
+        }
+        for p in self.packets.iter() {
+            writeln!(out, "{}", p)?;
+        }
+        for a in self.arrivals().unwrap_or(&[]) {
+            writeln!(out, "@ {} {}", a.tick, a.packet)?;
+        }
+        Ok(())
+    }
+    pub fn read<R: BufRead>(input: R) -> Result<Self, CaptureError> {
+        let mut lines = input.lines();
+        match lines.next() {
+            Some(Ok(header)) if header.trim() == MAGIC => {},
+            Some(Err(e)) => return Err(CaptureError::IO(e.to_string())),
+            _ => return Err(CaptureError::HEADER),
+        }
+        let mut capture = Capture::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| CaptureError::IO(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if i == 0 && line.trim() == FAULTS {
+                capture.track_arrivals();
+                continue;
+            }
+            let bad = || CaptureError::PACKET{ line: i + 2, text: line.clone() };
+            let arrival = line.trim_start().starts_with('@');
+            let fields: Vec<isize> = line.split_whitespace()
+                .skip(arrival as usize)
+                .map(|f| f.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| bad())?;
+            let order = || CaptureError::ORDER{
+                index: capture.len() + capture.arrivals().map_or(0, |a| a.len()),
+            };
+            match (arrival, &fields[..]) {
+                (false, &[tick, src, dst, x, y]) if tick >= 0 => {
+                    if capture.arrivals().is_some_and(|a| !a.is_empty())
+                            || capture.packets.last().is_some_and(|p| p.tick > tick as usize) {
+                        return Err(order());
+                    }
+                    capture.push(Packet{ tick: tick as usize, src, dst, x, y });
+                },
+                (true, &[at, tick, src, dst, x, y]) if tick >= 0 && at >= tick => {
+                    let a = Arrival{ tick: at as usize, packet: Packet{ tick: tick as usize, src, dst, x, y } };
+                    match capture.arrivals() {
+                        None => return Err(bad()),
+                        Some(arrivals) if arrivals.last().is_some_and(|b| b.tick > a.tick) => {
+                            return Err(order());
+                        },
+                        _ => capture.push_arrival(a),
+                    }
+                },
+                _ => return Err(bad()),
+            }
+        }
+        Ok(capture)
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CaptureError> {
+        let file = File::open(path).map_err(|e| CaptureError::IO(e.to_string()))?;
+        Capture::read(BufReader::new(file))
+    }
+    pub fn summary(&self) -> TrafficSummary {
+        let mut nodes: BTreeMap<isize, Traffic> = BTreeMap::new();
+        for p in self.packets.iter() {
+            nodes.entry(p.src).or_default().sent += 1;
+            if self.arrivals.is_none() {
+                nodes.entry(p.dst).or_default().received += 1;
+            }
+        }
+        for a in self.arrivals().unwrap_or(&[]) {
+            nodes.entry(a.packet.dst).or_default().received += 1;
+        }
+        let ticks = self.packets.last().map_or(0, |p| p.tick + 1);
+        TrafficSummary{ ticks, packets: self.len(), nodes }
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Traffic {
+    pub sent: usize,
+    pub received: usize,
+}
+
+// Packets sent and received by each address that saw any traffic
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct TrafficSummary {
+    pub ticks: usize,
+    pub packets: usize,
+    pub nodes: BTreeMap<isize, Traffic>,
+}
+impl TrafficSummary {
+    // Addresses by packets sent, busiest first
+    pub fn busiest(&self) -> Vec<isize> {
+        let mut addrs: Vec<isize> = self.nodes.keys().cloned().collect();
+        addrs.sort_by_key(|a| std::cmp::Reverse(self.nodes[a].sent));
+        addrs
+    }
+}
+impl fmt::Display for TrafficSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} packets over {} ticks", self.packets, self.ticks)?;
+        writeln!(f, "{:>5} {:>6} {:>8}", "addr", "sent", "received")?;
+        for (addr, t) in self.nodes.iter() {
+            let name = if *addr == NAT { "NAT".to_string() } else { addr.to_string() };
+            writeln!(f, "{:>5} {:>6} {:>8}", name, t.sent, t.received)?;
+        }
+        Ok(())
+    }
+}
+
+// Where the nodes under replay sent something other than what was captured
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Divergence {
+    pub tick: usize,
+    pub node: isize,
+    pub expected: Vec<Packet>,
+    pub observed: Vec<Packet>,
+}
+
+// Runs fresh nodes on captured traffic in place of their own. Each node
+// receives the packets it received when the capture was made, at the same
+// point in the same round, whatever the nodes send meanwhile; packets
+// from the NAT arrive at the end of their round.
+pub struct Replay {
+    net: Network,
+    pending: VecDeque<Packet>,
+    // What to deliver instead of the packets themselves, if the capture
+    // says what arrived
+    arrivals: Option<VecDeque<Arrival>>,
+    observed: Capture,
+    divergences: Vec<Divergence>,
+}
+impl Replay {
+    pub fn new(mem: &Vec<isize>, capture: &Capture) -> Result<Self, CaptureError> {
+        Replay::with_config(mem, capture, Config::new())
+    }
+    // Fails unless the capture could have come from a network set up like
+    // `config`: each round has the nodes' packets in node order, then the
+    // NAT's
+    pub fn with_config(mem: &Vec<isize>, capture: &Capture, config: Config)
+            -> Result<Self, CaptureError> {
+        let mut last = (0, 0);
+        for (index, p) in capture.packets().iter().enumerate() {
+            let turn = if p.src == config.nat {
+                config.nodes
+            } else if config.is_node(p.src) {
+                p.src as usize
+            } else {
+                return Err(CaptureError::SOURCE{ index, src: p.src });
+            };
+            if (p.tick, turn) < last {
+                return Err(CaptureError::ORDER{ index });
+            }
+            last = (p.tick, turn);
+        }
+        // Held up packets first, then the rest as they were sent
+        let mut last = (0, 0);
+        for (i, a) in capture.arrivals().unwrap_or(&[]).iter().enumerate() {
+            let index = capture.len() + i;
+            let turn = if a.is_delayed() {
+                0
+            } else if a.packet.src == config.nat {
+                config.nodes + 1
+            } else if config.is_node(a.packet.src) {
+                a.packet.src as usize + 1
+            } else {
+                return Err(CaptureError::SOURCE{ index, src: a.packet.src });
+            };
+            if (a.tick, turn) < last {
+                return Err(CaptureError::ORDER{ index });
+            }
+            last = (a.tick, turn);
+        }
+        Ok(Replay::unchecked(mem, capture, config))
+    }
+    fn unchecked(mem: &Vec<isize>, capture: &Capture, config: Config) -> Self {
+        Replay{
+            net: Network::with_config(mem, config),
+            pending: capture.packets().iter().cloned().collect(),
+            arrivals: capture.arrivals().map(|a| a.iter().cloned().collect()),
+            observed: Capture::new(),
+            divergences: Vec::new(),
+        }
+    }
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty() && self.arrivals.as_ref().is_none_or(|a| a.is_empty())
+    }
+    // Delivers captured arrivals due this round that `take` accepts, after
+    // dropping any left from earlier rounds
+    fn deliver_arrivals<F: Fn(&Arrival) -> bool>(&mut self, take: F) {
+        let tick = self.net.tick;
+        let arrivals = match self.arrivals.as_mut() {
+            Some(arrivals) => arrivals,
+            None => return,
+        };
+        let mut due = Vec::new();
+        while let Some(a) = arrivals.front().cloned() {
+            if a.tick > tick || (a.tick == tick && !take(&a)) {
+                break;
+            }
+            arrivals.pop_front();
+            if a.tick == tick {
+                due.push(a.packet);
+            }
+        }
+        for p in due {
+            if self.net.config().is_node(p.dst) {
+                self.net.deliver(&p);
+            }
+        }
+    }
+    // Delivers the captured packets `src` sent this round
+    fn inject_from(&mut self, src: isize) -> Vec<Packet> {
+        let tick = self.net.tick;
+        // Anything left from earlier rounds will never be sent; count it as
+        // missing so the replay still ends
+        while let Some(p) = self.pending.front().cloned() {
+            if p.tick >= tick {
+                break;
+            }
+            self.pending.pop_front();
+            self.divergences.push(Divergence{
+                tick: p.tick, node: p.src, expected: vec![p], observed: Vec::new(),
+            });
+        }
+        let mut expected = Vec::new();
+        while let Some(p) = self.pending.front().cloned() {
+            if p.tick != tick || p.src != src {
+                break;
+            }
+            self.pending.pop_front();
+            if self.arrivals.is_none() && self.net.config().is_node(p.dst) {
+                self.net.deliver(&p);
+            }
+            expected.push(p);
+        }
+        self.deliver_arrivals(|a| !a.is_delayed() && a.packet.src == src);
+        expected
+    }
+    // Runs one round
+    pub fn step(&mut self) {
+        self.deliver_arrivals(|a| a.is_delayed());
+        for i in 0..self.net.len() {
+            let observed = self.net.run_node(i);
+            let expected = self.inject_from(i as isize);
+            if observed != expected {
+                self.divergences.push(Divergence{
+                    tick: self.net.tick, node: i as isize, expected, observed: observed.clone(),
+                });
+            }
+            for p in observed {
+                self.observed.push(p);
+            }
+        }
+        self.inject_from(self.net.config().nat);
+        self.net.tick += 1;
+    }
+    // Runs until every captured packet has been delivered
+    pub fn run(&mut self) {
+        while !self.is_done() {
+            self.step();
+        }
+    }
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+    // What the nodes sent
+    pub fn observed(&self) -> &Capture {
+        &self.observed
+    }
+    pub fn divergences(&self) -> &[Divergence] {
+        &self.divergences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arrival, Capture, CaptureError, Packet, Replay};
+    use crate::{Faults, LinkFaults, Network, NAT};
+
+    // Each node reports its address to the NAT, then only reads
+    fn reporter() -> Vec<isize> {
+        vec![3,100, 104,255, 4,100, 4,100, 3,101, 1105,1,8]
+    }
+
+    fn capture_until_err(mem: &Vec<isize>) -> Capture {
+        let mut net = Network::new(mem);
+        net.start_capture();
+        assert_eq!(Err(49), net.loop_until_err());
+        net.take_capture().unwrap()
+    }
+
+    #[test]
+    fn captures_and_summarizes() {
+        let capture = capture_until_err(&reporter());
+        assert_eq!(51, capture.len());
+        assert_eq!(Packet{ tick: 1, src: NAT, dst: 0, x: 49, y: 49 }, capture.packets()[50]);
+        let summary = capture.summary();
+        assert_eq!(2, summary.ticks);
+        assert_eq!((1, 50), (summary.nodes[&NAT].sent, summary.nodes[&NAT].received));
+        assert_eq!((1, 1), (summary.nodes[&0].sent, summary.nodes[&0].received));
+        // Ties keep address order
+        assert_eq!(vec![0, 1], summary.busiest()[..2].to_vec());
+        assert!(summary.to_string().contains("  NAT      1       50\n"));
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let capture = capture_until_err(&reporter());
+        let mut out = Vec::new();
+        capture.write(&mut out).unwrap();
+        assert!(out.starts_with(b"cat6cap 1\n0 0 255 0 0\n"));
+        assert_eq!(Ok(capture), Capture::read(&out[..]));
+        assert_eq!(Err(CaptureError::HEADER), Capture::read(&b"0 0 255 0 0\n"[..]));
+        assert_eq!(Err(CaptureError::PACKET{ line: 3, text: "1 2 x 4 5".to_string() }),
+            Capture::read(&b"cat6cap 1\n1 2 3 4 5\n1 2 x 4 5\n"[..]));
+    }
+
+    #[test]
+    fn replays_into_fresh_nodes() {
+        let capture = capture_until_err(&reporter());
+        let mut replay = Replay::new(&reporter(), &capture).unwrap();
+        replay.run();
+        assert!(replay.divergences().is_empty());
+        assert_eq!(50, replay.observed().len());
+        assert_eq!(2, replay.network().tick());
+
+        // Every node now reports 7 instead of its address
+        let changed = vec![3,100, 104,255, 4,100, 104,7, 3,101, 1105,1,8];
+        let mut replay = Replay::new(&changed, &capture).unwrap();
+        replay.run();
+        // Node 7 is the one still right
+        assert_eq!(49, replay.divergences().len());
+        let d = &replay.divergences()[3];
+        assert_eq!((0, 3), (d.tick, d.node));
+        assert_eq!(3, d.expected[0].y);
+        assert_eq!(7, d.observed[0].y);
+    }
+
+    #[test]
+    fn rejects_impossible_captures() {
+        use crate::Config;
+        let read = |text: &str| Capture::read(text.as_bytes()).unwrap();
+        let unknown = read("cat6cap 1\n0 300 1 5 5\n");
+        let backwards = read("cat6cap 1\n0 5 1 5 5\n0 2 1 5 5\n");
+        assert_eq!(Some(CaptureError::SOURCE{ index: 0, src: 300 }),
+            Replay::new(&reporter(), &unknown).err());
+        assert_eq!(Some(CaptureError::ORDER{ index: 1 }),
+            Replay::new(&reporter(), &backwards).err());
+        assert_eq!(Err(CaptureError::ORDER{ index: 1 }),
+            Capture::read(&b"cat6cap 1\n1 0 1 5 5\n0 0 1 5 5\n"[..]));
+
+        // Replayed anyway, the packets that never come up count as missing
+        for capture in [unknown, backwards].iter() {
+            let mut replay = Replay::unchecked(&reporter(), capture, Config::new());
+            for _ in 0..10 {
+                replay.step();
+            }
+            assert!(replay.is_done());
+            assert!(replay.divergences().iter().any(|d| d.observed.is_empty()));
+        }
+    }
+
+    #[test]
+    fn captures_what_arrives() {
+        let faults = Faults::new(5).all(LinkFaults::new().latency(1).drop(0.2).duplicate(0.3));
+        let mut net = Network::new(&reporter()).with_faults(faults);
+        net.start_capture();
+        let p = net.run_until(|p| p.src == NAT).unwrap();
+        let stats = *net.faults().unwrap().stats();
+        let capture = net.take_capture().unwrap();
+        let arrivals = capture.arrivals().unwrap();
+        assert_eq!(stats.delivered, arrivals.len());
+        assert!(stats.dropped > 0 && stats.duplicated > 0);
+        assert!(arrivals.iter().all(Arrival::is_delayed));
+        let summary = capture.summary();
+        assert_eq!(capture.len(), summary.nodes.values().map(|n| n.sent).sum::<usize>());
+        assert_eq!(stats.delivered, summary.nodes.values().map(|n| n.received).sum::<usize>());
+
+        let mut out = Vec::new();
+        capture.write(&mut out).unwrap();
+        assert!(out.starts_with(b"cat6cap 1\nfaults\n0 0 255 0 0\n"));
+        assert_eq!(Ok(capture.clone()), Capture::read(&out[..]));
+        assert_eq!(Err(CaptureError::PACKET{ line: 2, text: "@ 1 0 0 255 0 0".to_string() }),
+            Capture::read(&b"cat6cap 1\n@ 1 0 0 255 0 0\n"[..]));
+        assert_eq!(Err(CaptureError::ORDER{ index: 2 }),
+            Capture::read(&b"cat6cap 1\nfaults\n0 0 255 0 0\n@ 2 0 0 255 0 0\n@ 1 0 0 255 0 0\n"[..]));
+
+        // Only what got through reaches the fresh nodes
+        let mut replay = Replay::new(&reporter(), &capture).unwrap();
+        replay.run();
+        assert!(replay.divergences().is_empty());
+        assert_eq!(capture.packets().iter().filter(|p| p.src != NAT).count(),
+            replay.observed().len());
+        assert!(replay.network().tick() > p.tick);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::NAT;
+
+// When the network counts as idle, so the NAT steps in
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum IdleRule {
+    // After this many rounds in a row in which no node had input waiting at
+    // its turn or sent anything
+    ROUNDS(usize),
+    // Each node counts its turns in a row with no input waiting and nothing
+    // sent; idle once every count reaches this many
+    PERNODE(usize),
+}
+
+// What the NAT sends to node 0 when the network is idle
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum NatMode {
+    // The last packet it received, again and again if nothing new arrives
+    LAST,
+    // Every packet received since it last sent, oldest first
+    ALL,
+    // The last packet it received, unless it already sent that one
+    DEDUP,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Config {
+    pub(crate) nodes: usize,
+    pub(crate) nat: isize,
+    pub(crate) idle: IdleRule,
+    pub(crate) nat_mode: NatMode,
+}
+impl Config {
+    // 50 nodes with the NAT at 255, stepping in after one idle round to
+    // resend the last packet it received
+    pub fn new() -> Self {
+        Config{ nodes: 50, nat: NAT, idle: IdleRule::ROUNDS(1), nat_mode: NatMode::LAST }
+    }
+    pub fn nodes(mut self, nodes: usize) -> Self {
+        assert!(nodes > 0, "precondition failed, a network needs a node");
+        self.nodes = nodes;
+        self
+    }
+    pub fn nat(mut self, addr: isize) -> Self {
+        self.nat = addr;
+        self
+    }
+    pub fn idle(mut self, rule: IdleRule) -> Self {
+        self.idle = rule;
+        self
+    }
+    pub fn nat_mode(mut self, mode: NatMode) -> Self {
+        self.nat_mode = mode;
+        self
+    }
+    pub fn is_node(&self, addr: isize) -> bool {
+        addr >= 0 && (addr as usize) < self.nodes
+    }
+}
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
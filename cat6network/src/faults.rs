@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Packet;
+
+// xorshift64*, seeded through splitmix64 so nearby seeds differ
+#[derive(Clone, Debug)]
+struct Rng {
+    state: u64,
+}
+impl Rng {
+    fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng{ state: if z == 0 { 1 } else { z } }
+    }
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    // True with probability `p`; draws nothing when `p` is zero
+    fn chance(&mut self, p: f64) -> bool {
+        p > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+// What can go wrong on a link. Everything is off by default.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct LinkFaults {
+    latency: usize,
+    drop: f64,
+    duplicate: f64,
+    reorder: f64,
+}
+impl LinkFaults {
+    pub fn new() -> Self {
+        LinkFaults::default()
+    }
+    // Rounds a packet spends in flight. Delayed packets arrive at the start
+    // of the round they are due, before any node runs.
+    pub fn latency(mut self, ticks: usize) -> Self {
+        self.latency = ticks;
+        self
+    }
+    pub fn drop(mut self, p: f64) -> Self {
+        self.drop = probability(p);
+        self
+    }
+    pub fn duplicate(mut self, p: f64) -> Self {
+        self.duplicate = probability(p);
+        self
+    }
+    // Chance a packet is held back a round, letting later ones overtake it
+    pub fn reorder(mut self, p: f64) -> Self {
+        self.reorder = probability(p);
+        self
+    }
+}
+
+fn probability(p: f64) -> f64 {
+    assert!((0.0..=1.0).contains(&p), "precondition failed, probability out of range");
+    p
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct FaultStats {
+    pub sent: usize,
+    pub delivered: usize,
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub delayed: usize,
+    pub reordered: usize,
+}
+impl fmt::Display for FaultStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sent, {} delivered, {} dropped, {} duplicated, {} delayed, {} reordered",
+            self.sent, self.delivered, self.dropped, self.duplicated, self.delayed, self.reordered)
+    }
+}
+
+// Seeded fault injection between the network and its links, so a run can
+// be repeated exactly
+#[derive(Clone, Debug)]
+pub struct Faults {
+    rng: Rng,
+    default: LinkFaults,
+    links: HashMap<(isize, isize), LinkFaults>,
+    // Packets with the round they are due, in the order sent
+    in_flight: Vec<(usize, Packet)>,
+    stats: FaultStats,
+}
+impl Faults {
+    pub fn new(seed: u64) -> Self {
+        Faults{
+            rng: Rng::new(seed),
+            default: LinkFaults::new(),
+            links: HashMap::new(),
+            in_flight: Vec::new(),
+            stats: FaultStats::default(),
+        }
+    }
+    // Faults for every link without its own
+    pub fn all(mut self, faults: LinkFaults) -> Self {
+        self.default = faults;
+        self
+    }
+    pub fn link(mut self, src: isize, dst: isize, faults: LinkFaults) -> Self {
+        self.links.insert((src, dst), faults);
+        self
+    }
+    pub fn stats(&self) -> &FaultStats {
+        &self.stats
+    }
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+    // Sends `p` in round `tick`, returning the copies that arrive right away
+    pub(crate) fn send(&mut self, p: Packet, tick: usize) -> Vec<Packet> {
+        let link = *self.links.get(&(p.src, p.dst)).unwrap_or(&self.default);
+        self.stats.sent += 1;
+        if self.rng.chance(link.drop) {
+            self.stats.dropped += 1;
+            return Vec::new();
+        }
+        let copies = if self.rng.chance(link.duplicate) {
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        let mut now = Vec::new();
+        for _ in 0..copies {
+            let mut delay = link.latency;
+            if self.rng.chance(link.reorder) {
+                self.stats.reordered += 1;
+                delay += 1;
+            }
+            if delay == 0 {
+                self.stats.delivered += 1;
+                now.push(p);
+            } else {
+                self.stats.delayed += 1;
+                self.in_flight.push((tick + delay, p));
+            }
+        }
+        now
+    }
+    // Takes the packets due by round `tick`, earliest due first
+    pub(crate) fn due(&mut self, tick: usize) -> Vec<Packet> {
+        let mut due: Vec<(usize, Packet)> = Vec::new();
+        self.in_flight.retain(|(at, p)| {
+            if *at <= tick {
+                due.push((*at, *p));
+            }
+            *at > tick
+        });
+        // Stable, so packets due together keep the order they were sent in
+        due.sort_by_key(|(at, _)| *at);
+        self.stats.delivered += due.len();
+        due.into_iter().map(|(_, p)| p).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Faults, LinkFaults, Rng};
+    use crate::Packet;
+
+    fn packet(y: isize) -> Packet {
+        Packet{ tick: 0, src: 1, dst: 2, x: 0, y }
+    }
+
+    #[test]
+    fn rng_is_seeded() {
+        let draws = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..5).map(|_| rng.next()).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(7), draws(7));
+        assert_ne!(draws(7), draws(8));
+    }
+
+    #[test]
+    fn latency_and_reordering() {
+        let mut faults = Faults::new(1).all(LinkFaults::new().latency(2))
+            .link(1, 2, LinkFaults::new().reorder(1.0));
+        assert_eq!(Vec::<Packet>::new(), faults.send(packet(1), 0));
+        let mut other = packet(2);
+        other.src = 3;
+        faults.send(other, 0);
+        assert_eq!(2, faults.in_flight());
+        // The reordered packet is due next round, the other in two
+        assert_eq!(vec![packet(1)], faults.due(1));
+        assert_eq!(vec![other], faults.due(2));
+        assert_eq!((2, 2, 1), (faults.stats().delivered, faults.stats().delayed,
+            faults.stats().reordered));
+    }
+
+    #[test]
+    fn drops_and_duplicates() {
+        let mut faults = Faults::new(1).all(LinkFaults::new().drop(1.0));
+        assert!(faults.send(packet(1), 0).is_empty());
+        let mut faults = Faults::new(1).all(LinkFaults::new().duplicate(1.0));
+        assert_eq!(vec![packet(1), packet(1)], faults.send(packet(1), 0));
+
+        // Half the packets make it, give or take, and the same ones each run
+        let survivors = |seed| {
+            let mut faults = Faults::new(seed).all(LinkFaults::new().drop(0.5));
+            (0..1000).filter(|y| !faults.send(packet(*y), 0).is_empty()).collect::<Vec<_>>()
+        };
+        let kept = survivors(42);
+        assert!(kept.len() > 400 && kept.len() < 600);
+        assert_eq!(kept, survivors(42));
+    }
+}
@@ -1,27 +1,107 @@
 mod intcode;
 use intcode::{Processor, Resources};
 
-struct Network {
+mod capture;
+pub use capture::{Arrival, Capture, CaptureError, Divergence, Packet, Replay, Traffic, TrafficSummary};
+
+mod config;
+pub use config::{Config, IdleRule, NatMode};
+
+mod faults;
+pub use faults::{FaultStats, Faults, LinkFaults};
+
+pub const NAT: isize = 255;
+
+pub struct Network {
+    config: Config,
     nodes: Vec<Resources>,
     proc: Processor,
-    nat_packet: (isize, isize),
-    last_y_delivered: isize,
+    // Packets the NAT holds on to, to send when the network goes idle
+    nat_queue: Vec<(isize, isize)>,
+    nat_sent: Option<(isize, isize)>,
+    idle_rounds: usize,
+    idle_counts: Vec<usize>,
+    stalled: bool,
+    misaddressed: usize,
+    tick: usize,
+    capture: Option<Capture>,
+    faults: Option<Faults>,
 }
 impl Network {
     pub fn new(mem: &Vec<isize>) -> Self {
+        Network::with_config(mem, Config::new())
+    }
+    pub fn with_config(mem: &Vec<isize>, config: Config) -> Self {
+        assert!(!config.is_node(config.nat), "precondition failed, NAT address belongs to a node");
         let proc = Processor::new_intcode();
         let mut nodes = Vec::new();
-        for i in 0..50 {
+        for i in 0..config.nodes {
             let mut res = Resources::new(mem.clone());
-            res.write_input(i);
+            res.write_input(i as isize);
             proc.execute(&mut res);
             nodes.push(res);
         }
         Network{
-            nodes: nodes, proc: proc, nat_packet: (0, 0),
-            last_y_delivered: 0,
+            config, nodes, proc,
+            nat_queue: Vec::new(), nat_sent: None,
+            idle_rounds: 0, idle_counts: vec![0; config.nodes],
+            stalled: false, misaddressed: 0, tick: 0, capture: None, faults: None,
+        }
+    }
+    // Routes every packet through `faults`
+    pub fn with_faults(mut self, faults: Faults) -> Self {
+        self.faults = Some(faults);
+        if let Some(capture) = self.capture.as_mut() {
+            capture.track_arrivals();
+        }
+        self
+    }
+    pub fn faults(&self) -> Option<&Faults> {
+        self.faults.as_ref()
+    }
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    // Rounds run so far
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+    // Turns in a row each node has been idle
+    pub fn idle_counts(&self) -> &[usize] {
+        &self.idle_counts
+    }
+    // Whether the last round left the network idle with nothing for the NAT
+    // to send, so nothing more will happen
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+    // Packets sent to addresses with no node or NAT, which are dropped
+    pub fn misaddressed(&self) -> usize {
+        self.misaddressed
+    }
+    // Logs every packet routed from now on, including the NAT's, and with
+    // faults on, every copy that arrives
+    pub fn start_capture(&mut self) {
+        if self.capture.is_none() {
+            let mut capture = Capture::new();
+            if self.faults.is_some() {
+                capture.track_arrivals();
+            }
+            self.capture = Some(capture);
         }
     }
+    pub fn capture(&self) -> Option<&Capture> {
+        self.capture.as_ref()
+    }
+    pub fn take_capture(&mut self) -> Option<Capture> {
+        self.capture.take()
+    }
     fn read_packet(&mut self, i: usize) -> Option<(isize, isize, isize)> {
         let res = &mut self.nodes[i];
         let dst = res.read_output()?;
@@ -34,33 +114,148 @@ impl Network {
         res.write_input(x);
         res.write_input(y);
     }
-    pub fn loop_once(&mut self) -> Result<(), isize> {
-        let mut idle = true;
+    // Gives node `i` its turn, returning the packets it sent
+    pub(crate) fn run_node(&mut self, i: usize) -> Vec<Packet> {
+        if self.nodes[i].input_len() == 0 {
+            self.nodes[i].write_input(-1);
+        }
+        self.proc.resume(&mut self.nodes[i]);
+        let mut sent = Vec::new();
+        while let Some((dst, x, y)) = self.read_packet(i) {
+            sent.push(Packet{ tick: self.tick, src: i as isize, dst, x, y });
+        }
+        sent
+    }
+    // Hands a packet to its destination node
+    pub(crate) fn deliver(&mut self, p: &Packet) {
+        self.send_packet(p.dst, p.x, p.y);
+    }
+    fn route(&mut self, p: Packet) {
+        if let Some(capture) = self.capture.as_mut() {
+            capture.push(p);
+        }
+        let arrived = match self.faults.as_mut() {
+            Some(faults) => faults.send(p, self.tick),
+            None => vec![p],
+        };
+        for p in arrived {
+            self.arrive(p);
+        }
+    }
+    fn arrive(&mut self, p: Packet) {
+        if let Some(capture) = self.capture.as_mut() {
+            capture.push_arrival(Arrival{ tick: self.tick, packet: p });
+        }
+        if p.dst == self.config.nat {
+            if self.config.nat_mode != NatMode::ALL {
+                self.nat_queue.clear();
+            }
+            self.nat_queue.push((p.x, p.y));
+        } else if self.config.is_node(p.dst) {
+            self.deliver(&p);
+        } else {
+            self.misaddressed += 1;
+        }
+    }
+    // Never with packets in flight
+    fn is_idle(&self) -> bool {
+        if self.faults.as_ref().is_some_and(|f| f.in_flight() > 0) {
+            return false;
+        }
+        match self.config.idle {
+            IdleRule::ROUNDS(n) => self.idle_rounds >= n,
+            IdleRule::PERNODE(n) => self.idle_counts.iter().all(|c| *c >= n),
+        }
+    }
+    fn nat_sends(&mut self) -> Vec<(isize, isize)> {
+        let last = self.nat_queue.last().cloned();
+        match self.config.nat_mode {
+            NatMode::LAST => last.into_iter().collect(),
+            NatMode::ALL => std::mem::take(&mut self.nat_queue),
+            NatMode::DEDUP => last.filter(|p| Some(*p) != self.nat_sent).into_iter().collect(),
+        }
+    }
+    // Runs a round, ending it early after the turn of a node that sends a
+    // packet `stop` accepts. That packet is returned instead of being sent.
+    pub fn loop_once_until<F: FnMut(&Packet) -> bool>(&mut self, stop: &mut F) -> Option<Packet> {
+        let due = match self.faults.as_mut() {
+            Some(faults) => faults.due(self.tick),
+            None => Vec::new(),
+        };
+        for p in due {
+            self.arrive(p);
+        }
+        let mut stopped = None;
+        let mut busy = false;
         for i in 0..self.nodes.len() {
-            if self.nodes[i].input_len() == 0 {
-                self.nodes[i].write_input(-1);
+            let mut quiet = self.nodes[i].input_len() == 0;
+            for p in self.run_node(i) {
+                quiet = false;
+                if stopped.is_none() && stop(&p) {
+                    stopped = Some(p);
+                } else {
+                    self.route(p);
+                }
+            }
+            if quiet {
+                self.idle_counts[i] += 1;
             } else {
-                idle = false;
+                self.idle_counts[i] = 0;
+                busy = true;
             }
-            self.proc.resume(&mut self.nodes[i]);
-            while let Some((dst, x, y)) = self.read_packet(i) {
-                idle = false;
-                if dst == 255 {
-                    self.nat_packet = (x, y);
-                } else {
-                    self.send_packet(dst, x, y);
+            if stopped.is_some() {
+                break;
+            }
+        }
+        if busy {
+            self.idle_rounds = 0;
+        } else {
+            self.idle_rounds += 1;
+        }
+        self.stalled = false;
+        if stopped.is_none() && self.is_idle() {
+            let sends = self.nat_sends();
+            self.stalled = sends.is_empty();
+            for (i, &(x, y)) in sends.iter().enumerate() {
+                let p = Packet{ tick: self.tick, src: self.config.nat, dst: 0, x, y };
+                if stop(&p) {
+                    stopped = Some(p);
+                    // What was not sent stays queued, as it does for LAST
+                    if self.config.nat_mode == NatMode::ALL {
+                        let rest = sends[i..].iter().cloned();
+                        self.nat_queue.splice(0..0, rest);
+                    }
+                    break;
                 }
+                self.nat_sent = Some((x, y));
+                self.route(p);
             }
         }
-        if idle {
-            let (x, y) = self.nat_packet;
-            if y == self.last_y_delivered {
-                return Err(y);
+        self.tick += 1;
+        stopped
+    }
+    // Runs until `stop` accepts a packet, which is returned unsent. None if
+    // the network stalls first.
+    pub fn run_until<F: FnMut(&Packet) -> bool>(&mut self, mut stop: F) -> Option<Packet> {
+        loop {
+            if let Some(p) = self.loop_once_until(&mut stop) {
+                return Some(p);
+            }
+            if self.stalled {
+                return None;
             }
-            self.send_packet(0, x, y);
-            self.last_y_delivered = y;
         }
-        Ok(())
+    }
+    // Stops when the NAT is about to send the y it sent last time, or would
+    // have if it had anything to send
+    pub fn loop_once(&mut self) -> Result<(), isize> {
+        let nat = self.config.nat;
+        let last = self.nat_sent.map_or(0, |(_, y)| y);
+        match self.loop_once_until(&mut |p: &Packet| p.src == nat && p.y == last) {
+            Some(p) => Err(p.y),
+            None if self.stalled => Err(last),
+            None => Ok(()),
+        }
     }
     pub fn loop_until_err(&mut self) -> Result<(), isize> {
         loop {
@@ -69,6 +264,14 @@ impl Network {
     }
 }
 
+pub fn y_val_of_first_nat_packet(mem: &Vec<isize>) -> isize {
+    let mut net = Network::new(mem);
+    match net.run_until(|p| p.dst == NAT) {
+        Some(p) => p.y,
+        None => panic!("expected a packet to the NAT"),
+    }
+}
+
 pub fn y_val_of_err_packet(mem: &Vec<isize>) -> isize {
     let mut net = Network::new(&mem);
     if let Err(y) = net.loop_until_err() {
@@ -78,10 +281,108 @@ pub fn y_val_of_err_packet(mem: &Vec<isize>) -> isize {
     }
 }
 
+// Runs the network to the same point as `y_val_of_err_packet`, logging
+// every packet on the way
+pub fn capture_traffic(mem: &Vec<isize>) -> Capture {
+    let mut net = Network::new(mem);
+    net.start_capture();
+    let _ = net.loop_until_err();
+    net.take_capture().unwrap()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{Config, Faults, IdleRule, LinkFaults, NatMode, Network, Packet};
+
+    // Each node sends its address to `dst` as x and y, then only reads
+    fn reporter(dst: isize) -> Vec<isize> {
+        vec![3,100, 104,dst, 4,100, 4,100, 3,101, 1105,1,8]
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn nat_address_and_node_count() {
+        let config = Config::new().nodes(3).nat(100);
+        let mut net = Network::with_config(&reporter(100), config);
+        assert_eq!(3, net.len());
+        assert_eq!(Some(Packet{ tick: 1, src: 100, dst: 0, x: 2, y: 2 }),
+            net.run_until(|p| p.src == 100));
+
+        // Nobody is at 255 to pick these up
+        let mut net = Network::with_config(&reporter(255), config);
+        assert_eq!(None, net.run_until(|_| false));
+        assert!(net.is_stalled());
+        assert_eq!(3, net.misaddressed());
+    }
+
+    #[test]
+    fn nat_modes() {
+        let config = Config::new().nodes(3);
+        let mut net = Network::with_config(&reporter(255), config);
+        assert_eq!(Err(2), net.loop_until_err());
+        assert_eq!(4, net.tick());
+
+        let mut net = Network::with_config(&reporter(255), config.nat_mode(NatMode::ALL));
+        net.start_capture();
+        assert_eq!(None, net.run_until(|_| false));
+        let sent: Vec<isize> = net.capture().unwrap().packets().iter()
+            .filter(|p| p.src == 255)
+            .map(|p| p.y)
+            .collect();
+        assert_eq!(vec![0, 1, 2], sent);
+
+        // Stopping partway through a batch keeps the rest for later
+        let mut net = Network::with_config(&reporter(255), config.nat_mode(NatMode::ALL));
+        net.start_capture();
+        assert_eq!(1, net.run_until(|p| p.src == 255 && p.y == 1).unwrap().y);
+        assert_eq!(None, net.run_until(|_| false));
+        let sent: Vec<isize> = net.capture().unwrap().packets().iter()
+            .filter(|p| p.src == 255)
+            .map(|p| p.y)
+            .collect();
+        assert_eq!(vec![0, 1, 2], sent);
+
+        let mut net = Network::with_config(&reporter(255), config.nat_mode(NatMode::DEDUP));
+        let mut nat_sends = 0;
+        assert_eq!(None, net.run_until(|p| {
+            nat_sends += (p.src == 255) as usize;
+            false
+        }));
+        assert_eq!((1, 4), (nat_sends, net.tick()));
+    }
+
+    #[test]
+    fn idle_rules() {
+        let first_nat_tick = |rule| {
+            let mut net = Network::with_config(&reporter(255), Config::new().nodes(3).idle(rule));
+            net.run_until(|p| p.src == 255).unwrap().tick
+        };
+        assert_eq!(1, first_nat_tick(IdleRule::ROUNDS(1)));
+        assert_eq!(3, first_nat_tick(IdleRule::ROUNDS(3)));
+        assert_eq!(2, first_nat_tick(IdleRule::PERNODE(2)));
+
+        let mut net = Network::with_config(&reporter(255), Config::new().nodes(3));
+        net.run_until(|p| p.src == 255);
+        assert_eq!(&[1, 1, 1], net.idle_counts());
+    }
+
+    #[test]
+    fn faulty_links() {
+        let config = Config::new().nodes(3);
+        // Nothing counts as idle while packets are in flight
+        let faults = Faults::new(3).all(LinkFaults::new().latency(2));
+        let mut net = Network::with_config(&reporter(255), config).with_faults(faults);
+        let p = net.run_until(|p| p.src == 255).unwrap();
+        assert_eq!((2, 2), (p.tick, p.y));
+        assert_eq!(3, net.faults().unwrap().stats().delivered);
+
+        let faults = Faults::new(3).link(2, 255, LinkFaults::new().drop(1.0));
+        let mut net = Network::with_config(&reporter(255), config).with_faults(faults);
+        assert_eq!(1, net.run_until(|p| p.src == 255).unwrap().y);
+        assert_eq!(1, net.faults().unwrap().stats().dropped);
+    }
 }
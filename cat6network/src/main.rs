@@ -1,7 +1,8 @@
 use std::io;
 use std::fs::read_to_string;
 
-use cat6network::y_val_of_err_packet;
+use cat6network::{capture_traffic, y_val_of_err_packet, y_val_of_first_nat_packet};
+use cat6network::{Faults, LinkFaults, Network, Replay};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -9,8 +10,34 @@ fn main() -> io::Result<()> {
         .map(|x| x.parse::<isize>().expect("failed to parse input"))
         .collect();
 
-    // Part 1 & 2
+    // Part 1
+    println!("y value of first NAT packet: {}", y_val_of_first_nat_packet(&mem));
+
+    // Part 2
     println!("y value of err packet: {}", y_val_of_err_packet(&mem));
 
+    let capture = capture_traffic(&mem);
+    capture.save("network.cap")?;
+    let summary = capture.summary();
+    let busiest: Vec<isize> = summary.busiest().into_iter().take(3).collect();
+    println!("{} packets over {} ticks, busiest addresses {:?}",
+        summary.packets, summary.ticks, busiest);
+    let mut replay = Replay::new(&mem, &capture).expect("network captures replay");
+    replay.run();
+    println!("replay diverged {} times", replay.divergences().len());
+
+    // How the nodes cope with faulty links
+    let scenarios = [
+        ("latency 3", LinkFaults::new().latency(3)),
+        ("reordering", LinkFaults::new().reorder(0.3)),
+        ("duplicates", LinkFaults::new().duplicate(0.1)),
+        ("1% loss", LinkFaults::new().drop(0.01)),
+    ];
+    for (name, link) in scenarios.iter() {
+        let mut net = Network::new(&mem).with_faults(Faults::new(2019).all(*link));
+        let result = net.loop_until_err();
+        println!("{}: {:?} after {} ticks; {}", name, result, net.tick(), net.faults().unwrap().stats());
+    }
+
     Ok(())
 }
@@ -1,11 +1,13 @@
 use std::f64;
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, BTreeMap, HashMap};
-use std::fmt;
 use std::io::BufRead;
 
 use num::integer::gcd;
 use itertools::Itertools;
+use gridmap::Grid;
+
+pub use gridmap::Point;
 
 fn float_cmp(a: &f64, b: &f64) -> Ordering {
     // A very paranoid choice of epsilon
@@ -20,35 +22,19 @@ fn float_cmp(a: &f64, b: &f64) -> Ordering {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Copy, Clone, Ord, PartialOrd)]
-pub struct Point {
-    x: i32,
-    y: i32,
-}
-impl Point {
-    pub fn new(x: i32, y: i32) -> Point {
-        Point{ x: x, y: y }
-    }
-    pub fn new_slope_vector(origin: &Point, p: &Point) -> Point {
-        let orig_x = p.x - origin.x;
-        let orig_y = p.y - origin.y;
-        let d = gcd(orig_x, orig_y);
-        Point{ x: orig_x / d, y: orig_y / d }
-    }
-    pub fn dist_manhattan(&self, rhs: &Point) -> i32 {
-        (self.x - rhs.x).abs() + (self.y - rhs.y).abs()
-    }
-}
-impl fmt::Display for Point {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
+// Direction from `origin` to `p`, reduced so that aligned points share it
+pub fn slope_vector(origin: &Point, p: &Point) -> Point {
+    let orig_x = p.x - origin.x;
+    let orig_y = p.y - origin.y;
+    let d = gcd(orig_x, orig_y);
+    Point::new(orig_x / d, orig_y / d)
 }
+
 #[derive(Eq, PartialEq, Debug)]
 struct ClockPoint(Point);
 impl ClockPoint {
     pub fn new_slope_vector(origin: &Point, p: &Point) -> ClockPoint {
-        ClockPoint(Point::new_slope_vector(origin, p))
+        ClockPoint(slope_vector(origin, p))
     }
     // Treating this point as a slope vector, what is the angle it forms with
     // the positive Y axis as zero, increasing clockwise, in range [0, 2*pi)?
@@ -75,15 +61,15 @@ impl PartialOrd for ClockPoint {
 }
 
 pub fn points_from_data<T: BufRead>(data: T) -> BTreeSet<Point> {
-    let mut asteriods = BTreeSet::new();
-    for (y, line) in data.lines().enumerate() {
-        for (x, ch) in line.expect("line read failed").trim().chars().enumerate() {
-            if ch == '#' {
-                asteriods.insert(Point::new(x as i32, y as i32));
-            }
-        }
-    }
-    asteriods
+    let text: String = data.lines()
+        .map(|l| l.expect("line read failed").trim().to_string() + "\n")
+        .collect();
+    let grid = Grid::parse(text.as_bytes(), |c| match c {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    }).expect("invalid asteriod map");
+    grid.iter().filter(|(_, ast)| **ast).map(|(p, _)| p).collect()
 }
 
 pub fn asteriod_visibility(asteriods: &BTreeSet<Point>) -> HashMap<&Point, Vec<Point>> {
@@ -93,7 +79,7 @@ pub fn asteriod_visibility(asteriods: &BTreeSet<Point>) -> HashMap<&Point, Vec<P
         let mut p1_visibility = Vec::new();
         for p2 in asteriods {
             if p1 != p2 {
-                p1_visibility.push(Point::new_slope_vector(&p1, &p2));
+                p1_visibility.push(slope_vector(&p1, &p2));
             }
         }
         all_visibility.push((p1, p1_visibility));
@@ -126,7 +112,7 @@ pub fn laser_blast_order<'a>(origin: &Point, other_asteriods: &'a BTreeSet<Point
     for aligned_asts in ast_map.values_mut() {
         aligned_asts.sort_by(
             // Farthest to closest so we can pop() the closest ones
-            |a, b| b.dist_manhattan(origin).cmp(&a.dist_manhattan(origin)));
+            |a, b| b.manhattan_distance(origin).cmp(&a.manhattan_distance(origin)));
     }
 
     // Scan the map vertically to iterate over the asteriods in blast order
@@ -158,7 +144,7 @@ pub fn laser_blast_order<'a>(origin: &Point, other_asteriods: &'a BTreeSet<Point
 mod tests {
     use std::f64;
     use std::collections::BTreeSet;
-    use super::{ClockPoint, Point, max_asteriod_visibility, points_from_data, laser_blast_order};
+    use super::{ClockPoint, Point, max_asteriod_visibility, points_from_data, laser_blast_order, slope_vector};
 
     const EXAMPLE1: &[u8] =
       b"......#.#.\n\
@@ -247,51 +233,59 @@ mod tests {
     fn clock_angle_within_range() {
         assert_between(
             0.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(1, -7).clock_angle(),
+            new_cp(1, -7).clock_angle(),
             1.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             1.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(7, -1).clock_angle(),
+            new_cp(7, -1).clock_angle(),
             2.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             2.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(7, 1).clock_angle(),
+            new_cp(7, 1).clock_angle(),
             3.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             3.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(1, 7).clock_angle(),
+            new_cp(1, 7).clock_angle(),
             4.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             4.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(-1, 7).clock_angle(),
+            new_cp(-1, 7).clock_angle(),
             5.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             5.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(-7, 1).clock_angle(),
+            new_cp(-7, 1).clock_angle(),
             6.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             6.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(-7, -1).clock_angle(),
+            new_cp(-7, -1).clock_angle(),
             7.0 * f64::consts::FRAC_PI_4,
         );
         assert_between(
             7.0 * f64::consts::FRAC_PI_4,
-            ClockPoint::new(-1, -7).clock_angle(),
+            new_cp(-1, -7).clock_angle(),
             8.0 * f64::consts::FRAC_PI_4,
         );
-        assert_eq!(0.0, ClockPoint::new(0, -1).clock_angle());
+        assert_eq!(0.0, new_cp(0, -1).clock_angle());
     }
 
     #[test]
     fn check_dist_manhattan() {
-        assert_eq!(2, Point::new(1, 1).dist_manhattan(&Point::new(2, 0)));
-        assert_eq!(7, Point::new(-3, 0).dist_manhattan(&Point::new(0, 4)));
+        assert_eq!(2, Point::new(1, 1).manhattan_distance(&Point::new(2, 0)));
+        assert_eq!(7, Point::new(-3, 0).manhattan_distance(&Point::new(0, 4)));
+    }
+
+    #[test]
+    fn check_slope_vector() {
+        let origin = Point::new(1, 1);
+        assert_eq!(Point::new(1, -1), slope_vector(&origin, &Point::new(3, -1)));
+        assert_eq!(Point::new(0, 1), slope_vector(&origin, &Point::new(1, 5)));
+        assert_eq!(Point::new(-3, 2), slope_vector(&origin, &Point::new(-2, 3)));
     }
 
     #[test]
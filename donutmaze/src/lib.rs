@@ -1,10 +1,13 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
 
+use graphsearch::bfs;
+use gridmap::{Grid, Point};
+
 type Pt = (usize, usize);
 
 struct Maze {
-    grid: Vec<Vec<char>>,
+    grid: Grid<char>,
     portals: HashMap<Pt, Pt>,
     inner_portals: HashSet<Pt>,
     start: Pt,
@@ -12,7 +15,7 @@ struct Maze {
 }
 impl Maze {
     pub fn from_data<T: BufRead>(data: T) -> Self {
-        let grid = Self::load_grid(data);
+        let grid = Grid::from_reader(data).expect("invalid maze");
         let (portals, in_ptls, start, end) =
             Self::build_portals_start_end(&grid);
         let mut maze = Maze{
@@ -27,23 +30,12 @@ impl Maze {
         print_grid(&maze.grid);
         maze
     }
-    fn load_grid<T: BufRead>(data: T) -> Vec<Vec<char>> {
-        let mut grid = Vec::new();
-        for line in data.lines() {
-            let mut row = Vec::new();
-            for ch in line.expect("line read failed").chars() {
-                row.push(ch);
-            }
-            grid.push(row);
-        }
-        grid
-    }
     fn build_portals_start_end(
-        grid: &Vec<Vec<char>>
+        grid: &Grid<char>
     ) -> (HashMap<Pt, Pt>, HashSet<Pt>, Pt, Pt) {
         let mut prtl_map = HashMap::new();
-        for y in 0..(grid.len()-1) {
-            for x in 0..(grid[0].len()-1) {
+        for y in 0..(grid.height()-1) {
+            for x in 0..(grid.width()-1) {
                 if grid[y][x].is_ascii_uppercase() {
                     if x > 0 && grid[y][x-1].is_ascii_uppercase() ||
                         y > 0 && grid[y-1][x].is_ascii_uppercase() {
@@ -83,11 +75,11 @@ impl Maze {
             portals.insert(pts[0], pts[1]);
             portals.insert(pts[1], pts[0]);
             if pts[0].0 != 2 && pts[0].1 != 2 &&
-                    pts[0].0 != grid[0].len()-3 && pts[0].1 != grid.len()-3 {
+                    pts[0].0 != grid.width()-3 && pts[0].1 != grid.height()-3 {
                 inner_portals.insert(pts[0]);
             }
             if pts[1].0 != 2 && pts[1].1 != 2 &&
-                    pts[1].0 != grid[0].len()-3 && pts[1].1 != grid.len()-3 {
+                    pts[1].0 != grid.width()-3 && pts[1].1 != grid.height()-3 {
                 inner_portals.insert(pts[1]);
             }
         }
@@ -98,19 +90,18 @@ impl Maze {
         let mut changed = true;
         while changed {
             changed = false;
-            for y in 1..self.grid.len()-1 {
-                for x in 1..self.grid[0].len()-1 {
-                    if self.grid[y][x] == '.' || self.grid[y][x].is_ascii_uppercase() {
-                        let mut blocked_sides = 0;
-                        if self.grid[y-1][x] == '#' { blocked_sides += 1; }
-                        if self.grid[y+1][x] == '#' { blocked_sides += 1; }
-                        if self.grid[y][x-1] == '#' { blocked_sides += 1; }
-                        if self.grid[y][x+1] == '#' { blocked_sides += 1; }
-                        if blocked_sides >= 3 {
-                            self.grid[y][x] = '#';
-                            changed = true;
-                        }
-                    }
+            let candidates: Vec<Point> = self.grid.iter()
+                .filter(|(p, &c)| (c == '.' || c.is_ascii_uppercase()) &&
+                    self.grid.neighbors4(*p).count() == 4)
+                .map(|(p, _)| p)
+                .collect();
+            for p in candidates {
+                let blocked_sides = self.grid.neighbors4(p)
+                    .filter(|n| self.grid[*n] == '#')
+                    .count();
+                if blocked_sides >= 3 {
+                    self.grid[p] = '#';
+                    changed = true;
                 }
             }
         }
@@ -123,81 +114,54 @@ impl Maze {
         self.grid[ay][ax] = '#';
         self.grid[zy][zx] = '#';
     }
-    fn find_adjacent_point(grid: &Vec<Vec<char>>, &(x, y): &Pt, c: char) -> Option<Pt> {
+    fn find_adjacent_point(grid: &Grid<char>, &(x, y): &Pt, c: char) -> Option<Pt> {
         if x > 0 && grid[y][x-1] == c {
             Some((x-1, y))
-        } else if x < grid[0].len()-1 && grid[y][x+1] == c {
+        } else if x < grid.width()-1 && grid[y][x+1] == c {
             Some((x+1, y))
         } else if y > 0 && grid[y-1][x] == c {
             Some((x, y-1))
-        } else if y < grid.len()-1 && grid[y+1][x] == c {
+        } else if y < grid.height()-1 && grid[y+1][x] == c {
             Some((x, y+1))
         } else {
             None
         }
     }
+    fn open_neighbors(&self, &(x, y): &Pt) -> Vec<Pt> {
+        self.grid.neighbors4(Point::from((x, y)))
+            .filter(|n| self.grid[*n] == '.')
+            .map(|n| (n.x as usize, n.y as usize))
+            .collect()
+    }
     pub fn basic_distance(&self, src: Pt, dst: Pt) -> Option<u64> {
-        let mut grid = self.grid.clone();
-        let mut q = VecDeque::new();
-        q.push_back((src, 0));
-        while !q.is_empty() {
-            let ((x, y), d) = q.pop_front().unwrap();
-            if x == dst.0 && y == dst.1 {
-                return Some(d)
-            } else if grid[y][x].is_ascii_uppercase() {
-                let adj_pt = Self::find_adjacent_point(&grid, &(x, y), '#')
-                    .expect("no adjacent point");
-                grid[adj_pt.1][adj_pt.0] = '#';
-                q.push_front((self.portals[&adj_pt], d));
-            } else if grid[y][x] == '.' {
-                grid[y][x] = '#';
-                q.push_back(((x-1, y), d+1));
-                q.push_back(((x+1, y), d+1));
-                q.push_back(((x, y-1), d+1));
-                q.push_back(((x, y+1), d+1));
-            }
-        }
-        None
+        let moves = |p: &Pt| {
+            let mut out = self.open_neighbors(p);
+            // Stepping through a portal costs the same as any other step
+            out.extend(self.portals.get(p));
+            out
+        };
+        bfs(src, moves, |p| *p == dst).map(|path| path.cost)
     }
     pub fn recursive_distance(&self, src: Pt, dst: Pt) -> Option<u64> {
-        let mut grids = Vec::new();
-        let mut q = VecDeque::new();
-        grids.push(self.grid.clone());
-        q.push_back((src, 0, 0));
-        while !q.is_empty() {
-            let ((x, y), d, lvl) = q.pop_front().unwrap();
-            if x == dst.0 && y == dst.1 && lvl == 0 {
-                return Some(d)
-            } else if grids[lvl][y][x].is_ascii_uppercase() {
-                let adj_pt = Self::find_adjacent_point(&grids[lvl], &(x, y), '#')
-                    .expect("no adjacent point");
-                grids[lvl][adj_pt.1][adj_pt.0] = '#';
-                if self.inner_portals.contains(&adj_pt) {
-                    grids.push(self.grid.clone());
-                    q.push_front((self.portals[&adj_pt], d, lvl+1));
+        let moves = |&(p, lvl): &(Pt, usize)| {
+            let mut out: Vec<(Pt, usize)> = self.open_neighbors(&p).into_iter()
+                .map(|n| (n, lvl))
+                .collect();
+            if let Some(&other) = self.portals.get(&p) {
+                if self.inner_portals.contains(&p) {
+                    out.push((other, lvl + 1));
                 } else if lvl > 0 {
-                    q.push_front((self.portals[&adj_pt], d, lvl-1));
+                    out.push((other, lvl - 1));
                 }
-            } else if grids[lvl][y][x] == '.' {
-                grids[lvl][y][x] = '#';
-                q.push_back(((x-1, y), d+1, lvl));
-                q.push_back(((x+1, y), d+1, lvl));
-                q.push_back(((x, y-1), d+1, lvl));
-                q.push_back(((x, y+1), d+1, lvl));
             }
-        }
-        None
+            out
+        };
+        bfs((src, 0), moves, |s| *s == (dst, 0)).map(|path| path.cost)
     }
 }
 
-fn print_grid(grid: &Vec<Vec<char>>) {
-    for v in grid.iter() {
-        for ch in v.iter() {
-            print!("{}", ch);
-        }
-        println!("");
-    }
-    println!("");
+fn print_grid(grid: &Grid<char>) {
+    println!("{}", grid);
 }
 
 pub fn find_shortest_path<T: BufRead>(data: T) -> Option<u64> {
@@ -1,76 +1,58 @@
 use std::collections::{HashSet, VecDeque};
 use std::io::BufRead;
 
-type Grid = [[bool; 5]; 5];
+use gridmap::Point;
+
+pub type Grid = gridmap::Grid<bool>;
+
+const SIZE: usize = 5;
+const CENTER: Point = Point{ x: 2, y: 2 };
 
 pub fn load_grid<T: BufRead>(data: T) -> Grid {
-    let mut arr = [[false; 5]; 5];
-    for (i, line) in data.lines().enumerate() {
-        for (j, ch) in line.expect("error reading line from file")
-                .trim().chars().enumerate() {
-            arr[i][j] = ch == '#';
-        }
-    }
-    arr
+    let text: String = data.lines()
+        .map(|l| l.expect("error reading line from file").trim().to_string() + "\n")
+        .collect();
+    Grid::parse(text.as_bytes(), |c| match c {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    }).expect("invalid bug grid")
 }
 
 pub fn print_grid(grid: &Grid) {
-    for i in 0..grid.len() {
-        for j in 0..grid[0].len() {
-            let ch = if grid[i][j] { '#' } else { '.' };
-            print!("{}", ch);
-        }
-        println!("");
-    }
+    print!("{}", grid.map(|b| if *b { '#' } else { '.' }));
 }
 
 pub fn print_multigrid(grids: &VecDeque<Grid>) {
     for g in grids.iter() {
-        println!("");
+        println!();
         print_grid(g);
-        println!("");
+        println!();
+    }
+}
+
+fn next_state(bug: bool, adj_bugs: u64) -> bool {
+    if bug {
+        adj_bugs == 1
+    } else {
+        adj_bugs == 1 || adj_bugs == 2
     }
 }
 
 fn iterate_grid(grid: &Grid) -> Grid {
     let mut new_grid = grid.clone();
-    for i in 0..grid.len() {
-        for j in 0..grid[0].len() {
-            let mut adj_bugs = 0;
-            if j > 0 && grid[j-1][i] {
-                adj_bugs += 1;
-            }
-            if j < grid[0].len()-1 && grid[j+1][i] {
-                adj_bugs += 1;
-            }
-            if i > 0 && grid[j][i-1] {
-                adj_bugs += 1;
-            }
-            if i < grid.len()-1 && grid[j][i+1] {
-                adj_bugs += 1;
-            }
-
-            if grid[j][i] && adj_bugs != 1 {
-                new_grid[j][i] = false;
-            } else if !grid[j][i] && (adj_bugs == 1 || adj_bugs == 2) {
-                new_grid[j][i] = true;
-            }
-        }
+    for p in grid.points() {
+        let adj_bugs = grid.neighbors4(p).filter(|n| grid[*n]).count() as u64;
+        new_grid[p] = next_state(grid[p], adj_bugs);
     }
     new_grid
 }
 
 fn biodiversity(grid: &Grid) -> u64 {
-    let mut b = 0;
-    for i in (0..grid.len()).rev() {
-        for j in (0..grid[0].len()).rev() {
-            b <<= 1;
-            if grid[i][j] {
-                b |= 1;
-            }
-        }
-    }
-    b
+    grid.iter()
+        .filter(|(_, bug)| **bug)
+        .map(|(p, _)| 1 << (p.y as usize * grid.width() + p.x as usize))
+        .fold(0, |b, bit| b | bit)
 }
 
 pub fn find_first_repeated(grid: &Grid) -> u64 {
@@ -94,63 +76,37 @@ fn count_bugs(grid: &Grid) -> u32 {
     biodiversity(grid).count_ones()
 }
 
-fn count_adjacent(grids: &VecDeque<Grid>, i: usize, j: usize, k: usize) -> u64 {
-    let mut cnt = 0;
-
-    // Assume a 5x5 grid for now
-    let (y, x) = (j as isize, k as isize);
-    if x == 2 && y == 2 {
+// Level i-1 surrounds level i, whose grid sits in the center tile of it
+fn count_adjacent(grids: &VecDeque<Grid>, i: usize, p: Point) -> u64 {
+    if p == CENTER {
         return 0;
     }
-    let squares_to_check = vec![(y-1, x), (y+1, x), (y, x-1), (y, x+1)];
-    for &(yy, xx) in squares_to_check.iter() {
-        let outer_bound = yy == -1 || yy == 5 || xx == -1 || xx == 5;
-        let inner_bound = yy == 2 && xx == 2;
-        if outer_bound {
-            if yy == -1 { // top outer edge
-                if i > 0 && grids[i-1][1][2] {
-                    cnt += 1;
-                }
-            } else if yy == 5 { // bottom outer edge
-                if i > 0 && grids[i-1][3][2] {
-                    cnt += 1;
-                }
-            } else if xx == -1 { // left outer edge
-                if i > 0 && grids[i-1][2][1] {
-                    cnt += 1;
-                }
-            } else if xx == 5 { // right outer edge
-                if i > 0 && grids[i-1][2][3] {
-                    cnt += 1;
-                }
-            }
-        } else if inner_bound {
-            if x == 1 { // left
-                for m in 0..5 {
-                    if i < grids.len()-1 && grids[i+1][m][0] {
-                        cnt += 1;
-                    }
-                }
-            } else if x == 3 { // right
-                for m in 0..5 {
-                    if i < grids.len()-1 && grids[i+1][m][4] {
-                        cnt += 1;
-                    }
-                }
-            } else if y == 1 { // top
-                for m in 0..5 {
-                    if i < grids.len()-1 && grids[i+1][0][m] {
-                        cnt += 1;
-                    }
-                }
-            } else if y == 3 { // bottom
-                for m in 0..5 {
-                    if i < grids.len()-1 && grids[i+1][4][m] {
-                        cnt += 1;
-                    }
-                }
+    let outer = if i > 0 { grids.get(i - 1) } else { None };
+    let inner = grids.get(i + 1);
+    let bug = |g: Option<&Grid>, q: Point| g.is_some_and(|g| g[q]);
+    let edge = (0..SIZE as i32).collect::<Vec<_>>();
+    let last = SIZE as i32 - 1;
+    let mut cnt = 0;
+    for n in p.neighbors4().iter() {
+        if !grids[i].contains(*n) {
+            // Off the edge is the tile next to the center one level out
+            let dir = Point::new(n.x - p.x, n.y - p.y);
+            if bug(outer, CENTER.offset(dir.x, dir.y)) {
+                cnt += 1;
             }
-        } else if grids[i][yy as usize][xx as usize] {
+        } else if *n == CENTER {
+            // The center is the whole facing edge one level in
+            let facing: Vec<Point> = if p.x == 1 {
+                edge.iter().map(|y| Point::new(0, *y)).collect()
+            } else if p.x == 3 {
+                edge.iter().map(|y| Point::new(last, *y)).collect()
+            } else if p.y == 1 {
+                edge.iter().map(|x| Point::new(*x, 0)).collect()
+            } else {
+                edge.iter().map(|x| Point::new(*x, last)).collect()
+            };
+            cnt += facing.into_iter().filter(|q| bug(inner, *q)).count() as u64;
+        } else if grids[i][*n] {
             cnt += 1;
         }
     }
@@ -160,22 +116,16 @@ fn count_adjacent(grids: &VecDeque<Grid>, i: usize, j: usize, k: usize) -> u64 {
 fn iterate_multigrid(grids: &VecDeque<Grid>) -> VecDeque<Grid> {
     let mut old_grids = grids.clone();
     if !empty(grids.front().expect("no grids")) {
-        old_grids.push_front([[false; 5]; 5]);
+        old_grids.push_front(Grid::new(SIZE, SIZE, false));
     }
     if !empty(grids.back().expect("no grids")) {
-        old_grids.push_back([[false; 5]; 5]);
+        old_grids.push_back(Grid::new(SIZE, SIZE, false));
     }
     let mut new_grids = old_grids.clone();
     for i in 0..old_grids.len() {
-        for j in 0..old_grids[0].len() {
-            for k in 0..old_grids[0][0].len() {
-                let adj_bugs = count_adjacent(&old_grids, i, j, k);
-                if old_grids[i][j][k] && adj_bugs != 1 {
-                    new_grids[i][j][k] = false;
-                } else if !old_grids[i][j][k] && (adj_bugs == 1 || adj_bugs == 2) {
-                    new_grids[i][j][k] = true;
-                }
-            }
+        for p in old_grids[i].points() {
+            let adj_bugs = count_adjacent(&old_grids, i, p);
+            new_grids[i][p] = p != CENTER && next_state(old_grids[i][p], adj_bugs);
         }
     }
     new_grids
@@ -185,13 +135,19 @@ pub fn find_num_bugs(grid: &Grid, n: usize) -> u32 {
     let mut grids = VecDeque::new();
     grids.push_back(grid.clone());
     for _ in 0..n {
-        grids = iterate_multigrid(&mut grids);
+        grids = iterate_multigrid(&grids);
     }
-    grids.iter().map(|g| count_bugs(g)).sum()
+    grids.iter().map(count_bugs).sum()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::Grid;
+
+    fn grid(rows: [[bool; 5]; 5]) -> Grid {
+        Grid::from_rows(rows.iter().map(|r| r.to_vec()).collect()).unwrap()
+    }
+
     #[test]
     fn check_read_from_file() {
         use super::load_grid;
@@ -201,58 +157,65 @@ mod tests {
             #..##\n\
             ..#..\n\
             #...." as &[u8];
-        assert_eq!(load_grid(BUF), [
+        assert_eq!(load_grid(BUF), grid([
             [false, false, false, false,  true],
             [ true, false, false,  true, false],
             [ true, false, false,  true,  true],
             [false, false,  true, false, false],
             [ true, false, false, false, false],
-        ]);
+        ]));
     }
 
     #[test]
     fn iterate_successfully() {
         use super::iterate_grid;
-        let grid = [
+        let g = grid([
             [false, false, false, false,  true],
             [ true, false, false,  true, false],
             [ true, false, false,  true,  true],
             [false, false,  true, false, false],
             [ true, false, false, false, false],
-        ];
-        let grid1 = iterate_grid(&grid);
-        assert_eq!(grid1, [
+        ]);
+        let grid1 = iterate_grid(&g);
+        assert_eq!(grid1, grid([
             [ true, false, false,  true, false],
             [ true,  true,  true,  true, false],
             [ true,  true,  true, false,  true],
             [ true,  true, false,  true,  true],
             [false,  true,  true, false, false],
-        ]);
+        ]));
     }
 
     #[test]
     fn check_biodiversity() {
         use super::biodiversity;
-        let grid = [
+        let g = grid([
             [false, false, false, false, false],
             [false, false, false, false, false],
             [false, false, false, false, false],
             [ true, false, false, false, false],
             [false,  true, false, false, false],
-        ];
-        assert_eq!(2129920, biodiversity(&grid));
+        ]);
+        assert_eq!(2129920, biodiversity(&g));
     }
 
     #[test]
     fn check_first_repeated() {
         use super::find_first_repeated;
-        let grid = [
+        let g = grid([
             [false, false, false, false,  true],
             [ true, false, false,  true, false],
             [ true, false, false,  true,  true],
             [false, false,  true, false, false],
             [ true, false, false, false, false],
-        ];
-        assert_eq!(2129920, find_first_repeated(&grid));
+        ]);
+        assert_eq!(2129920, find_first_repeated(&g));
+    }
+
+    #[test]
+    fn check_num_bugs_recursive() {
+        use super::{find_num_bugs, load_grid};
+        let g = load_grid(&b"....#\n#..#.\n#..##\n..#..\n#....\n"[..]);
+        assert_eq!(99, find_num_bugs(&g, 10));
     }
 }
@@ -0,0 +1,287 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+// States visited from start to goal, both ends included
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Path<S> {
+    pub states: Vec<S>,
+    pub cost: u64,
+}
+impl<S> Path<S> {
+    pub fn start(&self) -> &S {
+        &self.states[0]
+    }
+    pub fn goal(&self) -> &S {
+        &self.states[self.states.len() - 1]
+    }
+    // Number of moves, as opposed to the (possibly weighted) cost
+    pub fn len(&self) -> usize {
+        self.states.len() - 1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn reconstruct<S: Clone + Eq + Hash>(parents: &HashMap<S, S>, goal: S, cost: u64) -> Path<S> {
+    let mut states = vec![goal];
+    while let Some(prev) = parents.get(&states[states.len() - 1]) {
+        states.push(prev.clone());
+    }
+    states.reverse();
+    Path{ states, cost }
+}
+
+// Breadth-first search over unit-cost moves. The goal test runs as states
+// are discovered, so the search stops as early as possible.
+pub fn bfs<S, N, I, G>(start: S, mut successors: N, mut is_goal: G) -> Option<Path<S>>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              I: IntoIterator<Item=S>,
+              G: FnMut(&S) -> bool {
+    if is_goal(&start) {
+        return Some(Path{ states: vec![start], cost: 0 });
+    }
+    let mut parents = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start.clone());
+    queue.push_back((start, 0));
+    while let Some((s, d)) = queue.pop_front() {
+        for n in successors(&s) {
+            if seen.insert(n.clone()) {
+                parents.insert(n.clone(), s.clone());
+                if is_goal(&n) {
+                    return Some(reconstruct(&parents, n, d + 1));
+                }
+                queue.push_back((n, d + 1));
+            }
+        }
+    }
+    None
+}
+
+// Distance in moves to every state reachable from `start`
+pub fn bfs_distances<S, N, I>(start: S, mut successors: N) -> HashMap<S, u64>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              I: IntoIterator<Item=S> {
+    let mut dist = HashMap::new();
+    let mut queue = VecDeque::new();
+    dist.insert(start.clone(), 0);
+    queue.push_back((start, 0));
+    while let Some((s, d)) = queue.pop_front() {
+        for n in successors(&s) {
+            if !dist.contains_key(&n) {
+                dist.insert(n.clone(), d + 1);
+                queue.push_back((n, d + 1));
+            }
+        }
+    }
+    dist
+}
+
+// A* over weighted moves. `heuristic` must never overestimate the remaining
+// cost to a goal for the returned path to be the cheapest one.
+pub fn astar<S, N, I, H, G>(start: S, mut successors: N, mut heuristic: H, mut is_goal: G)
+        -> Option<Path<S>>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              I: IntoIterator<Item=(S, u64)>,
+              H: FnMut(&S) -> u64,
+              G: FnMut(&S) -> bool {
+    // Heap entries refer to `states` by index so S need not be ordered
+    let mut states = vec![start.clone()];
+    let mut best = HashMap::new();
+    let mut parents = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(start.clone(), 0);
+    heap.push(Reverse((heuristic(&start), 0, 0)));
+    while let Some(Reverse((_, g, idx))) = heap.pop() {
+        let s = states[idx].clone();
+        if best.get(&s).is_some_and(|b| *b < g) {
+            continue;
+        }
+        if is_goal(&s) {
+            return Some(reconstruct(&parents, s, g));
+        }
+        for (n, cost) in successors(&s) {
+            let ng = g + cost;
+            if best.get(&n).is_none_or(|b| ng < *b) {
+                best.insert(n.clone(), ng);
+                parents.insert(n.clone(), s.clone());
+                heap.push(Reverse((ng + heuristic(&n), ng, states.len())));
+                states.push(n);
+            }
+        }
+    }
+    None
+}
+
+pub fn dijkstra<S, N, I, G>(start: S, successors: N, is_goal: G) -> Option<Path<S>>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              I: IntoIterator<Item=(S, u64)>,
+              G: FnMut(&S) -> bool {
+    astar(start, successors, |_| 0, is_goal)
+}
+
+// Cheapest cost to every state reachable from `start`
+pub fn dijkstra_distances<S, N, I>(start: S, mut successors: N) -> HashMap<S, u64>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              I: IntoIterator<Item=(S, u64)> {
+    let mut states = vec![start.clone()];
+    let mut best = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(start, 0);
+    heap.push(Reverse((0, 0)));
+    while let Some(Reverse((g, idx))) = heap.pop() {
+        let s = states[idx].clone();
+        if best[&s] < g {
+            continue;
+        }
+        for (n, cost) in successors(&s) {
+            let ng = g + cost;
+            if best.get(&n).is_none_or(|b| ng < *b) {
+                best.insert(n.clone(), ng);
+                heap.push(Reverse((ng, states.len())));
+                states.push(n);
+            }
+        }
+    }
+    best
+}
+
+// Breadth-first search growing from both ends, one layer at a time from
+// whichever side has the smaller frontier. `predecessors` lists the states
+// that can move into a given state; for undirected moves it is the same
+// function as `successors`.
+pub fn bidirectional_bfs<S, N, P, I, J>(start: S, goal: S, mut successors: N, mut predecessors: P)
+        -> Option<Path<S>>
+        where S: Clone + Eq + Hash,
+              N: FnMut(&S) -> I,
+              P: FnMut(&S) -> J,
+              I: IntoIterator<Item=S>,
+              J: IntoIterator<Item=S> {
+    if start == goal {
+        return Some(Path{ states: vec![start], cost: 0 });
+    }
+    // Depth of each state from its own end, and the neighbor it was reached from
+    let mut fwd: HashMap<S, (u64, Option<S>)> = HashMap::new();
+    let mut bwd: HashMap<S, (u64, Option<S>)> = HashMap::new();
+    fwd.insert(start.clone(), (0, None));
+    bwd.insert(goal.clone(), (0, None));
+    let mut fwd_frontier = vec![start];
+    let mut bwd_frontier = vec![goal];
+    while !fwd_frontier.is_empty() && !bwd_frontier.is_empty() {
+        let forward = fwd_frontier.len() <= bwd_frontier.len();
+        let (frontier, this, other) = if forward {
+            (&mut fwd_frontier, &mut fwd, &bwd)
+        } else {
+            (&mut bwd_frontier, &mut bwd, &fwd)
+        };
+        let mut next = Vec::new();
+        let mut meeting: Option<(u64, S)> = None;
+        for s in frontier.drain(..) {
+            let d = this[&s].0;
+            let neighbors: Vec<S> = if forward {
+                successors(&s).into_iter().collect()
+            } else {
+                predecessors(&s).into_iter().collect()
+            };
+            for n in neighbors {
+                if this.contains_key(&n) {
+                    continue;
+                }
+                this.insert(n.clone(), (d + 1, Some(s.clone())));
+                if let Some((od, _)) = other.get(&n) {
+                    let total = d + 1 + od;
+                    if meeting.as_ref().is_none_or(|(best, _)| total < *best) {
+                        meeting = Some((total, n.clone()));
+                    }
+                }
+                next.push(n);
+            }
+        }
+        if let Some((cost, mid)) = meeting {
+            let mut states = vec![mid.clone()];
+            while let Some((_, Some(prev))) = fwd.get(&states[states.len() - 1]) {
+                states.push(prev.clone());
+            }
+            states.reverse();
+            let mut cur = mid;
+            while let Some((_, Some(after))) = bwd.get(&cur) {
+                states.push(after.clone());
+                cur = after.clone();
+            }
+            return Some(Path{ states, cost });
+        }
+        *frontier = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{astar, bfs, bfs_distances, bidirectional_bfs, dijkstra, dijkstra_distances};
+
+    // 0..10 on a line, where every third number can also jump ahead by 3
+    fn line_moves(n: &i32) -> Vec<i32> {
+        let mut out = vec![n - 1, n + 1];
+        if n % 3 == 0 {
+            out.push(n + 3);
+        }
+        out.into_iter().filter(|m| *m >= 0 && *m < 10).collect()
+    }
+
+    #[test]
+    fn bfs_finds_shortest_path() {
+        let path = bfs(0, line_moves, |n| *n == 7).unwrap();
+        assert_eq!(vec![0, 3, 6, 7], path.states);
+        assert_eq!(3, path.cost);
+        assert_eq!(3, path.len());
+        assert_eq!((&0, &7), (path.start(), path.goal()));
+        assert_eq!(None, bfs(0, line_moves, |n| *n == 12));
+        assert!(bfs(4, line_moves, |n| *n == 4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn bfs_distances_cover_reachable() {
+        let dist = bfs_distances(9, line_moves);
+        assert_eq!(10, dist.len());
+        assert_eq!(Some(&9), dist.get(&0));
+        assert_eq!(Some(&6), dist.get(&3));
+    }
+
+    #[test]
+    fn weighted_searches() {
+        // Jumps cost 5, so walking is cheaper
+        let moves = |n: &i32| line_moves(n).into_iter()
+            .map(|m| (m, if m - n == 3 { 5 } else { 1 }))
+            .collect::<Vec<_>>();
+        let path = dijkstra(0, moves, |n| *n == 7).unwrap();
+        assert_eq!(7, path.cost);
+        let path = astar(0, moves, |n| (7 - n).unsigned_abs() as u64, |n| *n == 7).unwrap();
+        assert_eq!(7, path.cost);
+        assert_eq!(8, path.states.len());
+        let dist = dijkstra_distances(0, moves);
+        assert_eq!(Some(&9), dist.get(&9));
+    }
+
+    #[test]
+    fn bidirectional_matches_bfs() {
+        let backwards = |n: &i32| (0..10).filter(|m| line_moves(m).contains(n)).collect::<Vec<_>>();
+        for goal in 0..10 {
+            let path = bidirectional_bfs(0, goal, line_moves, backwards).unwrap();
+            assert_eq!(bfs(0, line_moves, |n| *n == goal).unwrap().cost, path.cost);
+            assert_eq!(path.cost as usize, path.len());
+            assert_eq!((&0, &goal), (path.start(), path.goal()));
+            for w in path.states.windows(2) {
+                assert!(line_moves(&w[0]).contains(&w[1]));
+            }
+        }
+        assert_eq!(None, bidirectional_bfs(0, 20, line_moves, backwards));
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::BufRead;
+use std::ops::{Index, IndexMut};
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug, Default)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+impl Point {
+    pub fn new(x: i32, y: i32) -> Self {
+        Point{ x, y }
+    }
+    pub fn offset(&self, dx: i32, dy: i32) -> Point {
+        Point::new(self.x + dx, self.y + dy)
+    }
+    pub fn manhattan_distance(&self, other: &Point) -> u32 {
+        ((self.x - other.x).abs() + (self.y - other.y).abs()) as u32
+    }
+    // Orthogonal neighbors, in the order up, right, down, left (screen axes)
+    pub fn neighbors4(&self) -> [Point; 4] {
+        [self.offset(0, -1), self.offset(1, 0), self.offset(0, 1), self.offset(-1, 0)]
+    }
+    // Orthogonal and diagonal neighbors, clockwise from up
+    pub fn neighbors8(&self) -> [Point; 8] {
+        [
+            self.offset(0, -1), self.offset(1, -1), self.offset(1, 0), self.offset(1, 1),
+            self.offset(0, 1), self.offset(-1, 1), self.offset(-1, 0), self.offset(-1, -1),
+        ]
+    }
+}
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+impl From<(usize, usize)> for Point {
+    fn from((x, y): (usize, usize)) -> Self {
+        Point::new(x as i32, y as i32)
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum GridError {
+    EMPTY,
+    RAGGED { line: usize, expected: usize, found: usize },
+    SYMBOL { line: usize, col: usize, ch: char },
+    IO(String),
+}
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::EMPTY => write!(f, "grid has no cells"),
+            GridError::RAGGED{ line, expected, found } => write!(f,
+                "line {} has {} cells, expected {}", line, found, expected),
+            GridError::SYMBOL{ line, col, ch } => write!(f,
+                "unexpected {:?} at line {}, column {}", ch, line, col),
+            GridError::IO(msg) => write!(f, "read failed: {}", msg),
+        }
+    }
+}
+impl error::Error for GridError {}
+
+// Dense, bounded grid stored row by row; (0, 0) is the top-left cell.
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct Grid<T> {
+    w: usize,
+    h: usize,
+    cells: Vec<T>,
+}
+impl<T: Clone> Grid<T> {
+    pub fn new(w: usize, h: usize, fill: T) -> Self {
+        Grid{ w, h, cells: vec![fill; w * h] }
+    }
+    pub fn transposed(&self) -> Self {
+        let cells = (0..self.w)
+            .flat_map(|x| (0..self.h).map(move |y| (x, y)))
+            .map(|(x, y)| self.cells[y * self.w + x].clone())
+            .collect();
+        Grid{ w: self.h, h: self.w, cells }
+    }
+    pub fn flipped_horizontal(&self) -> Self {
+        let cells = self.rows()
+            .flat_map(|row| row.iter().rev().cloned())
+            .collect();
+        Grid{ w: self.w, h: self.h, cells }
+    }
+    pub fn flipped_vertical(&self) -> Self {
+        let cells = self.rows().rev()
+            .flat_map(|row| row.iter().cloned())
+            .collect();
+        Grid{ w: self.w, h: self.h, cells }
+    }
+    pub fn rotated_cw(&self) -> Self {
+        self.transposed().flipped_horizontal()
+    }
+    pub fn rotated_ccw(&self) -> Self {
+        self.transposed().flipped_vertical()
+    }
+}
+impl<T> Grid<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, GridError> {
+        let w = rows.first().map(|r| r.len()).unwrap_or(0);
+        let h = rows.len();
+        let mut cells = Vec::with_capacity(w * h);
+        for (line, row) in rows.into_iter().enumerate() {
+            if row.len() != w {
+                return Err(GridError::RAGGED{ line, expected: w, found: row.len() });
+            }
+            cells.extend(row);
+        }
+        Ok(Grid{ w, h, cells })
+    }
+    // One row per line; `symbol` maps each character to a cell and returns
+    // None for characters that are not allowed. Trailing blank lines are
+    // ignored, every other line must have the same length.
+    pub fn parse<R, F>(data: R, mut symbol: F) -> Result<Self, GridError>
+            where R: BufRead, F: FnMut(char) -> Option<T> {
+        let mut rows = Vec::new();
+        for (line, text) in data.lines().enumerate() {
+            let text = text.map_err(|e| GridError::IO(e.to_string()))?;
+            let row = text.chars().enumerate()
+                .map(|(col, ch)| symbol(ch).ok_or(GridError::SYMBOL{ line, col, ch }))
+                .collect::<Result<Vec<T>, GridError>>()?;
+            rows.push(row);
+        }
+        while rows.last().map(|r| r.is_empty()).unwrap_or(false) {
+            rows.pop();
+        }
+        if rows.is_empty() {
+            return Err(GridError::EMPTY);
+        }
+        Grid::from_rows(rows)
+    }
+    pub fn width(&self) -> usize {
+        self.w
+    }
+    pub fn height(&self) -> usize {
+        self.h
+    }
+    pub fn contains(&self, p: Point) -> bool {
+        p.x >= 0 && (p.x as usize) < self.w && p.y >= 0 && (p.y as usize) < self.h
+    }
+    pub fn get(&self, p: Point) -> Option<&T> {
+        if self.contains(p) {
+            Some(&self.cells[p.y as usize * self.w + p.x as usize])
+        } else {
+            None
+        }
+    }
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        if self.contains(p) {
+            Some(&mut self.cells[p.y as usize * self.w + p.x as usize])
+        } else {
+            None
+        }
+    }
+    // Returns false if `p` is outside the grid
+    pub fn set(&mut self, p: Point, v: T) -> bool {
+        match self.get_mut(p) {
+            Some(cell) => {
+                *cell = v;
+                true
+            },
+            None => false,
+        }
+    }
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.cells.chunks(self.w.max(1))
+    }
+    pub fn points(&self) -> impl Iterator<Item=Point> {
+        let (w, h) = (self.w, self.h);
+        (0..h).flat_map(move |y| (0..w).map(move |x| Point::from((x, y))))
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(Point, &T)> {
+        self.points().zip(self.cells.iter())
+    }
+    pub fn position<F: Fn(&T) -> bool>(&self, pred: F) -> Option<Point> {
+        self.iter().find(|(_, v)| pred(v)).map(|(p, _)| p)
+    }
+    pub fn neighbors4(&self, p: Point) -> impl Iterator<Item=Point> + '_ {
+        p.neighbors4().to_vec().into_iter().filter(move |n| self.contains(*n))
+    }
+    pub fn neighbors8(&self, p: Point) -> impl Iterator<Item=Point> + '_ {
+        p.neighbors8().to_vec().into_iter().filter(move |n| self.contains(*n))
+    }
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid{ w: self.w, h: self.h, cells: self.cells.iter().map(f).collect() }
+    }
+}
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+    fn index(&self, p: Point) -> &T {
+        self.get(p).expect("point outside of grid")
+    }
+}
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, p: Point) -> &mut T {
+        self.get_mut(p).expect("point outside of grid")
+    }
+}
+// Row access, so `grid[y][x]` works as it does for nested vectors
+impl<T> Index<usize> for Grid<T> {
+    type Output = [T];
+    fn index(&self, y: usize) -> &[T] {
+        &self.cells[y * self.w..(y + 1) * self.w]
+    }
+}
+impl<T> IndexMut<usize> for Grid<T> {
+    fn index_mut(&mut self, y: usize) -> &mut [T] {
+        &mut self.cells[y * self.w..(y + 1) * self.w]
+    }
+}
+impl Grid<char> {
+    pub fn from_reader<R: BufRead>(data: R) -> Result<Self, GridError> {
+        Grid::parse(data, Some)
+    }
+}
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows().take(self.h) {
+            for c in row.iter() {
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Unbounded grid holding only the cells that have been set.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point, T>,
+}
+impl<T> SparseGrid<T> {
+    pub fn new() -> Self {
+        SparseGrid{ cells: HashMap::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    pub fn contains(&self, p: Point) -> bool {
+        self.cells.contains_key(&p)
+    }
+    pub fn get(&self, p: Point) -> Option<&T> {
+        self.cells.get(&p)
+    }
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        self.cells.get_mut(&p)
+    }
+    pub fn set(&mut self, p: Point, v: T) -> Option<T> {
+        self.cells.insert(p, v)
+    }
+    pub fn remove(&mut self, p: Point) -> Option<T> {
+        self.cells.remove(&p)
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(Point, &T)> {
+        self.cells.iter().map(|(p, v)| (*p, v))
+    }
+    // Top-left and bottom-right corners of the cells that are set
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut pts = self.cells.keys();
+        let first = *pts.next()?;
+        Some(pts.fold((first, first), |(lo, hi), p| (
+            Point::new(lo.x.min(p.x), lo.y.min(p.y)),
+            Point::new(hi.x.max(p.x), hi.y.max(p.y)),
+        )))
+    }
+    // Neighbors that have been set
+    pub fn neighbors4(&self, p: Point) -> impl Iterator<Item=Point> + '_ {
+        p.neighbors4().to_vec().into_iter().filter(move |n| self.contains(*n))
+    }
+    pub fn neighbors8(&self, p: Point) -> impl Iterator<Item=Point> + '_ {
+        p.neighbors8().to_vec().into_iter().filter(move |n| self.contains(*n))
+    }
+    pub fn render<F: Fn(Option<&T>) -> char>(&self, symbol: F) -> String {
+        let mut out = String::new();
+        if let Some((lo, hi)) = self.bounds() {
+            for y in lo.y..=hi.y {
+                out.extend((lo.x..=hi.x).map(|x| symbol(self.get(Point::new(x, y)))));
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+impl<T: Clone> SparseGrid<T> {
+    // Dense copy covering the bounds, along with the point that the dense
+    // grid's (0, 0) corresponds to
+    pub fn to_grid(&self, fill: T) -> Option<(Grid<T>, Point)> {
+        let (lo, hi) = self.bounds()?;
+        let mut grid = Grid::new((hi.x - lo.x + 1) as usize, (hi.y - lo.y + 1) as usize, fill);
+        for (p, v) in self.iter() {
+            grid.set(p.offset(-lo.x, -lo.y), v.clone());
+        }
+        Some((grid, lo))
+    }
+}
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Grid, GridError, Point, SparseGrid};
+
+    const SMALL: &[u8] = b"#.@\n..#\n\n";
+
+    #[test]
+    fn parse_and_display() {
+        let grid = Grid::from_reader(SMALL).unwrap();
+        assert_eq!((3, 2), (grid.width(), grid.height()));
+        assert_eq!('@', grid[Point::new(2, 0)]);
+        assert_eq!('#', grid[1][2]);
+        assert_eq!(Some(Point::new(2, 0)), grid.position(|c| *c == '@'));
+        assert_eq!("#.@\n..#\n", format!("{}", grid));
+        assert_eq!("(2, 0)", Point::new(2, 0).to_string());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(GridError::RAGGED{ line: 1, expected: 2, found: 3 }),
+            Grid::from_reader(b"..\n...\n" as &[u8]));
+        assert_eq!(Err(GridError::SYMBOL{ line: 0, col: 1, ch: 'x' }),
+            Grid::parse(b".x" as &[u8], |c| if c == '.' { Some(false) } else { None }));
+        assert_eq!(Err(GridError::EMPTY), Grid::from_reader(b"\n" as &[u8]));
+    }
+
+    #[test]
+    fn neighbors_are_bounds_checked() {
+        let grid = Grid::new(3, 3, 0);
+        assert_eq!(2, grid.neighbors4(Point::new(0, 0)).count());
+        assert_eq!(4, grid.neighbors4(Point::new(1, 1)).count());
+        assert_eq!(3, grid.neighbors8(Point::new(2, 2)).count());
+        assert_eq!(8, grid.neighbors8(Point::new(1, 1)).count());
+        assert_eq!(None, grid.get(Point::new(-1, 0)));
+        assert_eq!(None, grid.get(Point::new(0, 3)));
+    }
+
+    #[test]
+    fn rotate_and_flip() {
+        let grid = Grid::from_reader(b"ab\ncd\nef\n" as &[u8]).unwrap();
+        assert_eq!("eca\nfdb\n", format!("{}", grid.rotated_cw()));
+        assert_eq!("bdf\nace\n", format!("{}", grid.rotated_ccw()));
+        assert_eq!("ba\ndc\nfe\n", format!("{}", grid.flipped_horizontal()));
+        assert_eq!("ef\ncd\nab\n", format!("{}", grid.flipped_vertical()));
+        assert_eq!(grid, grid.rotated_cw().rotated_cw().rotated_cw().rotated_cw());
+        assert_eq!("ace\nbdf\n", format!("{}", grid.transposed()));
+    }
+
+    #[test]
+    fn map_and_set() {
+        let mut grid = Grid::from_reader(SMALL).unwrap().map(|c| *c == '#');
+        assert!(grid.set(Point::new(1, 1), true));
+        assert!(!grid.set(Point::new(3, 1), true));
+        assert_eq!(3, grid.iter().filter(|(_, v)| **v).count());
+    }
+
+    #[test]
+    fn sparse_grid() {
+        let mut sparse = SparseGrid::new();
+        assert_eq!(None, sparse.bounds());
+        sparse.set(Point::new(-2, 1), '#');
+        sparse.set(Point::new(1, -1), 'o');
+        sparse.set(Point::new(-1, 1), '#');
+        assert_eq!(Some((Point::new(-2, -1), Point::new(1, 1))), sparse.bounds());
+        assert_eq!(1, sparse.neighbors4(Point::new(-2, 1)).count());
+        assert_eq!("...o\n....\n##..\n",
+            sparse.render(|c| *c.unwrap_or(&'.')));
+        let (dense, origin) = sparse.to_grid(' ').unwrap();
+        assert_eq!(Point::new(-2, -1), origin);
+        assert_eq!('o', dense[0][3]);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+
+pub use gridmap::Point;
+
+// Which way y grows: SCREEN has row 0 at the top, CARTESIAN has y pointing up
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Orientation {
+    SCREEN,
+    CARTESIAN,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Turn {
+    STRAIGHT,
+    LEFT,
+    RIGHT,
+    AROUND,
+}
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub enum Heading {
+    UP,
+    RIGHT,
+    DOWN,
+    LEFT,
+}
+impl Heading {
+    pub const ALL: [Heading; 4] = [Heading::UP, Heading::RIGHT, Heading::DOWN, Heading::LEFT];
+
+    fn index(self) -> usize {
+        match self {
+            Heading::UP => 0,
+            Heading::RIGHT => 1,
+            Heading::DOWN => 2,
+            Heading::LEFT => 3,
+        }
+    }
+    pub fn turned(self, t: Turn) -> Heading {
+        let quarter_turns = match t {
+            Turn::STRAIGHT => 0,
+            Turn::RIGHT => 1,
+            Turn::AROUND => 2,
+            Turn::LEFT => 3,
+        };
+        Heading::ALL[(self.index() + quarter_turns) % 4]
+    }
+    pub fn reversed(self) -> Heading {
+        self.turned(Turn::AROUND)
+    }
+    // The turn that takes this heading to `other`
+    pub fn turn_to(self, other: Heading) -> Turn {
+        match (other.index() + 4 - self.index()) % 4 {
+            0 => Turn::STRAIGHT,
+            1 => Turn::RIGHT,
+            2 => Turn::AROUND,
+            _ => Turn::LEFT,
+        }
+    }
+    pub fn delta(self, o: Orientation) -> (i32, i32) {
+        let up = match o {
+            Orientation::SCREEN => -1,
+            Orientation::CARTESIAN => 1,
+        };
+        match self {
+            Heading::UP => (0, up),
+            Heading::RIGHT => (1, 0),
+            Heading::DOWN => (0, -up),
+            Heading::LEFT => (-1, 0),
+        }
+    }
+    pub fn step_from(self, p: Point, o: Orientation) -> Point {
+        let (dx, dy) = self.delta(o);
+        p.offset(dx, dy)
+    }
+    pub fn from_char(c: char) -> Option<Heading> {
+        match c {
+            '^' => Some(Heading::UP),
+            '>' => Some(Heading::RIGHT),
+            'v' => Some(Heading::DOWN),
+            '<' => Some(Heading::LEFT),
+            _ => None,
+        }
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            Heading::UP => '^',
+            Heading::RIGHT => '>',
+            Heading::DOWN => 'v',
+            Heading::LEFT => '<',
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Bounds {
+    INFINITE,
+    // Cells with 0 <= x < w and 0 <= y < h
+    RECT { w: i32, h: i32 },
+}
+impl Bounds {
+    pub fn contains(&self, p: &Point) -> bool {
+        match self {
+            Bounds::INFINITE => true,
+            Bounds::RECT{ w, h } => p.x >= 0 && p.x < *w && p.y >= 0 && p.y < *h,
+        }
+    }
+}
+
+// Anything the robot can query about the cell it is on (or looking at).
+// Closures taking the cell and the robot's heading work as sensors.
+pub trait Sensor {
+    type Reading;
+    fn sense(&mut self, at: Point, heading: Heading) -> Self::Reading;
+}
+impl<R, F: FnMut(Point, Heading) -> R> Sensor for F {
+    type Reading = R;
+    fn sense(&mut self, at: Point, heading: Heading) -> R {
+        self(at, heading)
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Robot {
+    pos: Point,
+    heading: Heading,
+    orientation: Orientation,
+    bounds: Bounds,
+    steps: usize,
+}
+impl Robot {
+    pub fn new(pos: Point, heading: Heading) -> Self {
+        Robot{
+            pos,
+            heading,
+            orientation: Orientation::SCREEN,
+            bounds: Bounds::INFINITE,
+            steps: 0,
+        }
+    }
+    pub fn with_orientation(mut self, o: Orientation) -> Self {
+        self.orientation = o;
+        self
+    }
+    pub fn with_bounds(mut self, b: Bounds) -> Self {
+        assert!(b.contains(&self.pos), "precondition failed, robot starts out of bounds");
+        self.bounds = b;
+        self
+    }
+    pub fn pos(&self) -> Point {
+        self.pos
+    }
+    pub fn heading(&self) -> Heading {
+        self.heading
+    }
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+    // Number of cells moved so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+    pub fn turn(&mut self, t: Turn) {
+        self.heading = self.heading.turned(t);
+    }
+    pub fn face(&mut self, h: Heading) {
+        self.heading = h;
+    }
+    // The cell the robot would reach by turning and moving one step, if it
+    // is within bounds. Does not move the robot.
+    pub fn ahead(&self, t: Turn) -> Option<Point> {
+        let p = self.heading.turned(t).step_from(self.pos, self.orientation);
+        if self.bounds.contains(&p) { Some(p) } else { None }
+    }
+    // Returns false, leaving the robot in place, when blocked by the bounds
+    pub fn forward(&mut self) -> bool {
+        match self.ahead(Turn::STRAIGHT) {
+            Some(p) => {
+                self.pos = p;
+                self.steps += 1;
+                true
+            },
+            None => false,
+        }
+    }
+    pub fn turn_and_forward(&mut self, t: Turn) -> bool {
+        self.turn(t);
+        self.forward()
+    }
+    pub fn step_in(&mut self, h: Heading) -> bool {
+        self.face(h);
+        self.forward()
+    }
+    pub fn sense<S: Sensor>(&self, sensor: &mut S) -> S::Reading {
+        sensor.sense(self.pos, self.heading)
+    }
+    pub fn sense_ahead<S: Sensor>(&self, t: Turn, sensor: &mut S) -> Option<S::Reading> {
+        let heading = self.heading.turned(t);
+        self.ahead(t).map(|p| sensor.sense(p, heading))
+    }
+}
+impl fmt::Display for Robot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.heading.to_char())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bounds, Heading, Orientation, Point, Robot, Turn};
+
+    #[test]
+    fn can_turn() {
+        let mut h = Heading::LEFT;
+        h = h.turned(Turn::LEFT).turned(Turn::LEFT);
+        assert_eq!(Heading::RIGHT, h);
+        h = h.turned(Turn::RIGHT);
+        assert_eq!(Heading::DOWN, h);
+        assert_eq!(Heading::UP, h.reversed());
+        assert_eq!(Heading::DOWN, h.turned(Turn::STRAIGHT));
+        for a in Heading::ALL.iter() {
+            for b in Heading::ALL.iter() {
+                assert_eq!(*b, a.turned(a.turn_to(*b)));
+            }
+        }
+    }
+
+    #[test]
+    fn moves_by_orientation() {
+        let mut screen = Robot::new(Point::new(0, 0), Heading::UP);
+        assert!(screen.forward());
+        assert_eq!(Point::new(0, -1), screen.pos());
+        let mut cartesian = Robot::new(Point::new(0, 0), Heading::UP)
+            .with_orientation(Orientation::CARTESIAN);
+        assert!(cartesian.turn_and_forward(Turn::STRAIGHT));
+        assert!(cartesian.turn_and_forward(Turn::RIGHT));
+        assert_eq!(Point::new(1, 1), cartesian.pos());
+        assert_eq!(2, cartesian.steps());
+    }
+
+    #[test]
+    fn blocked_by_bounds() {
+        let mut robot = Robot::new(Point::new(1, 0), Heading::UP)
+            .with_bounds(Bounds::RECT{ w: 2, h: 2 });
+        assert_eq!(None, robot.ahead(Turn::STRAIGHT));
+        assert!(!robot.forward());
+        assert_eq!(Point::new(1, 0), robot.pos());
+        assert_eq!(Some(Point::new(0, 0)), robot.ahead(Turn::LEFT));
+        assert!(robot.step_in(Heading::DOWN));
+        assert_eq!(Point::new(1, 1), robot.pos());
+        assert!(!robot.step_in(Heading::RIGHT));
+    }
+
+    #[test]
+    fn pluggable_sensors() {
+        let walls = [Point::new(1, 0)];
+        let mut wall_sensor = |p: Point, _| walls.contains(&p);
+        let robot = Robot::new(Point::new(0, 0), Heading::RIGHT);
+        assert!(!robot.sense(&mut wall_sensor));
+        assert_eq!(Some(true), robot.sense_ahead(Turn::STRAIGHT, &mut wall_sensor));
+        let mut compass = |_, h: Heading| h.to_char();
+        assert_eq!(Some('v'), robot.sense_ahead(Turn::RIGHT, &mut compass));
+        assert_eq!(">", format!("{}", robot));
+    }
+
+    #[test]
+    fn heading_chars() {
+        for h in Heading::ALL.iter() {
+            assert_eq!(Some(*h), Heading::from_char(h.to_char()));
+        }
+        assert_eq!(None, Heading::from_char('#'));
+    }
+}
@@ -80,6 +80,10 @@ impl Resources {
     pub fn write_mem(&mut self, loc: isize, val: isize) {
         self.mem[loc as usize] = val;
     }
+    // Words of memory held by this program state
+    pub fn mem_size(&self) -> usize {
+        self.mem.len()
+    }
     pub fn dump_mem(&self) -> Vec<isize> {
         self.mem.clone()
     }
@@ -1,13 +1,29 @@
-use std::collections::{VecDeque, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use graphsearch::{bfs, bfs_distances};
+
+use gridmap::SparseGrid;
+use gridrobot::{Heading, Orientation, Point, Robot};
 
 mod intcode;
 use intcode::{Processor, Resources};
 
+mod shipmap;
+pub use shipmap::{tile_palette, ShipMap, Tile, OXYGENATED};
+
+mod oxygen;
+pub use oxygen::Spread;
+
 // Inputs
-const NORTH: isize = 1;
-const SOUTH: isize = 2;
-const WEST: isize = 3;
-const EAST: isize = 4;
+fn movement_command(h: Heading) -> isize {
+    match h {
+        Heading::UP => 1,
+        Heading::DOWN => 2,
+        Heading::LEFT => 3,
+        Heading::RIGHT => 4,
+    }
+}
 
 // Outputs
 const WALL: isize = 0;
@@ -20,57 +36,39 @@ enum MoveResult {
     OXYGEN(RepairDroid),
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-impl Point {
-    pub fn new(x: i32, y: i32) -> Self {
-        Point{ x: x, y: y, }
-    }
-}
-
 struct RepairDroid {
     proc: Processor,
     res: Resources,
-    moves: u32,
-    point: Point,
+    bot: Robot,
 }
 impl RepairDroid {
     pub fn new(mem: Vec<isize>) -> Self {
         let mut droid = RepairDroid{
             proc: Processor::new_intcode(),
             res: Resources::new(mem),
-            moves: 0,
-            point: Point::new(0, 0),
+            bot: Robot::new(Point::new(0, 0), Heading::UP),
         };
         droid.proc.execute(&mut droid.res);
         droid
     }
-    fn from_move(&self, dir: isize, new_res: Resources) -> Self {
+    fn from_move(&self, dir: Heading, new_res: Resources) -> Self {
+        let mut bot = self.bot.clone();
+        bot.step_in(dir);
         RepairDroid{
             proc: Processor::new_intcode(),
             res: new_res,
-            moves: self.moves + 1,
-            point: match dir {
-                NORTH => Point::new(self.point.x, self.point.y-1),
-                SOUTH => Point::new(self.point.x, self.point.y+1),
-                WEST => Point::new(self.point.x-1, self.point.y),
-                EAST => Point::new(self.point.x+1, self.point.y),
-                _ => panic!("bad direction"),
-            },
+            bot,
         }
     }
     pub fn moves(&self) -> u32 {
-        self.moves
+        self.bot.steps() as u32
     }
-    pub fn point(&self) -> &Point {
-        &self.point
+    pub fn point(&self) -> Point {
+        self.bot.pos()
     }
-    pub fn move_in_dir(&self, dir: isize) -> MoveResult {
+    pub fn move_in_dir(&self, dir: Heading) -> MoveResult {
         let mut new_res = self.res.clone();
-        new_res.write_input(dir);
+        new_res.write_input(movement_command(dir));
         self.proc.resume(&mut new_res);
         match new_res.read_output().expect("no output") {
             WALL => MoveResult::WALL,
@@ -79,57 +77,201 @@ impl RepairDroid {
             _ => panic!("bad intcode program result"),
         }
     }
+    // Moves this droid instead of forking a new one, reporting what it found
+    pub fn step(&mut self, dir: Heading) -> Tile {
+        self.res.write_input(movement_command(dir));
+        self.proc.resume(&mut self.res);
+        let tile = match self.res.read_output().expect("no output") {
+            WALL => Tile::WALL,
+            MOVE => Tile::OPEN,
+            OXYGEN => Tile::OXYGEN,
+            _ => panic!("bad intcode program result"),
+        };
+        if tile != Tile::WALL {
+            self.bot.step_in(dir);
+        }
+        tile
+    }
     pub fn reset_loc(&mut self) {
-        self.point = Point::new(0, 0);
-        self.moves = 0;
+        self.bot = Robot::new(Point::new(0, 0), Heading::UP);
     }
 }
 
-fn bfs_find_oxygen(d: RepairDroid) -> Option<RepairDroid> {
-    let mut nodes_queue = VecDeque::new();
-    nodes_queue.push_back(d);
-    let mut points_seen: HashSet<Point> = HashSet::new();
-    while !nodes_queue.is_empty() {
-        let droid = nodes_queue.pop_front().unwrap();
-        if !points_seen.contains(droid.point()) {
-            points_seen.insert(droid.point().clone());
-            for &dir in [NORTH, SOUTH, WEST, EAST].iter() {
-                match droid.move_in_dir(dir) {
-                    MoveResult::WALL => (),
+// Droids parked at each explored point, so the search can resume the
+// program from wherever a point was first reached. Whatever the droids
+// bump into along the way is recorded in `tiles`.
+struct Fleet {
+    droids: RefCell<HashMap<Point, RepairDroid>>,
+    oxygen: Cell<Option<Point>>,
+    tiles: RefCell<SparseGrid<Tile>>,
+    commands: Cell<usize>,
+    moves: Cell<usize>,
+}
+impl Fleet {
+    pub fn new(d: RepairDroid) -> Self {
+        let mut tiles = SparseGrid::new();
+        tiles.set(d.point(), Tile::OPEN);
+        let mut droids = HashMap::new();
+        droids.insert(d.point(), d);
+        Fleet{
+            droids: RefCell::new(droids),
+            oxygen: Cell::new(None),
+            tiles: RefCell::new(tiles),
+            commands: Cell::new(0),
+            moves: Cell::new(0),
+        }
+    }
+    pub fn explore(&self, p: &Point) -> Vec<Point> {
+        let found: Vec<RepairDroid> = {
+            let droids = self.droids.borrow();
+            let droid = &droids[p];
+            let mut tiles = self.tiles.borrow_mut();
+            self.commands.set(self.commands.get() + Heading::ALL.len());
+            Heading::ALL.iter()
+                .filter_map(|&dir| match droid.move_in_dir(dir) {
+                    MoveResult::WALL => {
+                        tiles.set(dir.step_from(*p, Orientation::SCREEN), Tile::WALL);
+                        None
+                    },
                     MoveResult::MOVE(new_droid) => {
-                        nodes_queue.push_back(new_droid);
+                        self.moves.set(self.moves.get() + 1);
+                        tiles.set(new_droid.point(), Tile::OPEN);
+                        Some(new_droid)
                     },
                     MoveResult::OXYGEN(new_droid) => {
-                        return Some(new_droid);
-                    }
-                }
-            }
+                        self.moves.set(self.moves.get() + 1);
+                        tiles.set(new_droid.point(), Tile::OXYGEN);
+                        self.oxygen.set(Some(new_droid.point()));
+                        Some(new_droid)
+                    },
+                })
+                .collect()
+        };
+        let mut droids = self.droids.borrow_mut();
+        found.into_iter()
+            .map(|d| {
+                let pt = d.point();
+                droids.entry(pt).or_insert(d);
+                pt
+            })
+            .collect()
+    }
+    pub fn take(self, p: &Point) -> Option<RepairDroid> {
+        self.droids.into_inner().remove(p)
+    }
+    // The explored tiles as a dense map, shifted so the corner is (0, 0)
+    pub fn to_map(&self) -> ShipMap {
+        let tiles = self.tiles.borrow();
+        let (grid, origin) = tiles.to_grid(Tile::UNKNOWN).expect("droid start is always recorded");
+        ShipMap::new(grid, Some(Point::new(-origin.x, -origin.y)))
+    }
+    pub fn stats(&self) -> ExploreStats {
+        let droids = self.droids.borrow();
+        ExploreStats{
+            commands: self.commands.get(),
+            moves: self.moves.get(),
+            droids: droids.len(),
+            memory_words: droids.values().map(|d| d.res.mem_size()).sum(),
         }
     }
-    None
 }
 
-fn bfs_farthest_distance(d: RepairDroid) -> u32 {
-    let mut max_dist = 0;
-    let mut nodes_queue = VecDeque::new();
-    nodes_queue.push_back(d);
-    let mut points_seen: HashSet<Point> = HashSet::new();
-    while !nodes_queue.is_empty() {
-        let droid = nodes_queue.pop_front().unwrap();
-        if !points_seen.contains(droid.point()) {
-            points_seen.insert(droid.point().clone());
-            max_dist = if max_dist < droid.moves() { droid.moves() } else { max_dist };
-            for &dir in [NORTH, SOUTH, WEST, EAST].iter() {
-                match droid.move_in_dir(dir) {
-                    MoveResult::WALL => (),
-                    MoveResult::MOVE(new_droid) | MoveResult::OXYGEN(new_droid) => {
-                        nodes_queue.push_back(new_droid);
-                    },
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Strategy {
+    // Forks a droid, program state and all, for every open cell
+    BFS,
+    // Drives a single droid, backing up along its own trail at dead ends
+    DFS,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct ExploreStats {
+    // Movement commands fed to intcode programs, wall bumps included
+    pub commands: usize,
+    // Commands that moved a droid
+    pub moves: usize,
+    // Program states kept alive at the end of the exploration
+    pub droids: usize,
+    pub memory_words: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Exploration {
+    pub map: ShipMap,
+    pub stats: ExploreStats,
+}
+
+fn explore_bfs(d: RepairDroid) -> Exploration {
+    let start = d.point();
+    let fleet = Fleet::new(d);
+    bfs_distances(start, |p| fleet.explore(p));
+    Exploration{ map: fleet.to_map(), stats: fleet.stats() }
+}
+
+fn explore_dfs(mut d: RepairDroid) -> Exploration {
+    let mut tiles = SparseGrid::new();
+    tiles.set(d.point(), Tile::OPEN);
+    let mut trail: Vec<Heading> = Vec::new();
+    let mut commands = 0;
+    loop {
+        let here = d.point();
+        let unexplored = Heading::ALL.iter().cloned()
+            .find(|h| !tiles.contains(h.step_from(here, Orientation::SCREEN)));
+        match unexplored {
+            Some(dir) => {
+                commands += 1;
+                let tile = d.step(dir);
+                tiles.set(dir.step_from(here, Orientation::SCREEN), tile);
+                if tile != Tile::WALL {
+                    trail.push(dir);
                 }
-            }
+            },
+            None => match trail.pop() {
+                Some(dir) => {
+                    commands += 1;
+                    let tile = d.step(dir.reversed());
+                    assert!(tile != Tile::WALL, "postcondition failed, trail blocked");
+                },
+                None => break,
+            },
         }
     }
-    max_dist
+    let (grid, origin) = tiles.to_grid(Tile::UNKNOWN).expect("droid start is always recorded");
+    let start = d.point().offset(-origin.x, -origin.y);
+    let stats = ExploreStats{
+        commands,
+        moves: d.moves() as usize,
+        droids: 1,
+        memory_words: d.res.mem_size(),
+    };
+    Exploration{ map: ShipMap::new(grid, Some(start)), stats }
+}
+
+pub fn explore(mem: Vec<isize>, strategy: Strategy) -> Exploration {
+    let d = RepairDroid::new(mem);
+    match strategy {
+        Strategy::BFS => explore_bfs(d),
+        Strategy::DFS => explore_dfs(d),
+    }
+}
+
+fn bfs_find_oxygen(d: RepairDroid) -> Option<RepairDroid> {
+    let start = d.point();
+    let fleet = Fleet::new(d);
+    let path = bfs(start, |p| fleet.explore(p), |p| fleet.oxygen.get() == Some(*p))?;
+    fleet.take(path.goal())
+}
+
+fn bfs_farthest_distance(d: RepairDroid) -> u32 {
+    let start = d.point();
+    let fleet = Fleet::new(d);
+    let dist = bfs_distances(start, |p| fleet.explore(p));
+    dist.values().cloned().max().unwrap_or(0) as u32
+}
+
+// Visits every reachable cell, so the map also covers the walls around them
+pub fn explore_map(mem: Vec<isize>) -> ShipMap {
+    explore(mem, Strategy::BFS).map
 }
 
 pub fn min_distance_to_oxygen(mem: Vec<isize>) -> Option<u32> {
@@ -157,4 +299,58 @@ mod tests {
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn strategies_agree() {
+        use super::{explore, Strategy, Tile};
+        use gridrobot::Point;
+        // Reports a wall for every command, boxing the droid in
+        let boxed_in = vec![3,10,104,0,1105,1,0];
+        let bfs = explore(boxed_in.clone(), Strategy::BFS);
+        let dfs = explore(boxed_in, Strategy::DFS);
+        assert_eq!(bfs.map, dfs.map);
+        assert_eq!(Some(Point::new(1, 1)), dfs.map.start());
+        assert_eq!(Tile::WALL, dfs.map.tile(Point::new(1, 0)));
+        assert_eq!(Tile::UNKNOWN, dfs.map.tile(Point::new(0, 0)));
+        assert_eq!((4, 0, 1), (bfs.stats.commands, bfs.stats.moves, bfs.stats.droids));
+        assert_eq!((4, 0, 1), (dfs.stats.commands, dfs.stats.moves, dfs.stats.droids));
+    }
+
+    #[test]
+    fn strategies_agree_on_ship() {
+        use std::fs::read_to_string;
+        use super::{explore, min_distance_to_oxygen, Strategy};
+        let input = read_to_string("input.txt").unwrap();
+        let mem: Vec<isize> = input.trim().split(',')
+            .map(|x| x.parse::<isize>().expect("failed to parse input"))
+            .collect();
+        // A real maze, full of branches and dead ends to back out of
+        let bfs = explore(mem.clone(), Strategy::BFS);
+        let dfs = explore(mem.clone(), Strategy::DFS);
+        assert_eq!(bfs.map, dfs.map);
+        // Backing out of every dead end walks each passage twice
+        let open = dfs.map.grid().iter().filter(|(_, t)| t.is_open()).count();
+        assert_eq!(2 * (open - 1), dfs.stats.moves);
+        assert_eq!(1, dfs.stats.droids);
+        assert!(bfs.stats.droids > 1);
+        let (start, oxygen) = (dfs.map.start().unwrap(), dfs.map.oxygen().unwrap());
+        let distance = dfs.map.distance(start, oxygen);
+        assert_eq!(bfs.map.distance(start, oxygen), distance);
+        assert_eq!(min_distance_to_oxygen(mem).map(|d| d as u64), distance);
+    }
+
+    #[test]
+    fn droid_tracks_moves() {
+        use super::{MoveResult, movement_command};
+        use gridrobot::{Heading, Point};
+        // Echoes each input back as the status, so moving north (1) reports a move
+        let droid = RepairDroid::new(vec![3,20,4,20,1105,1,0]);
+        assert_eq!(1, movement_command(Heading::UP));
+        let moved = match droid.move_in_dir(Heading::UP) {
+            MoveResult::MOVE(d) => d,
+            _ => panic!("expected move"),
+        };
+        assert_eq!(Point::new(0, -1), moved.point());
+        assert_eq!(1, moved.moves());
+    }
 }
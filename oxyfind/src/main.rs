@@ -1,7 +1,10 @@
 use std::io;
-use std::fs::read_to_string;
+use std::fs::{read_to_string, write, File};
+use std::io::BufReader;
 
-use oxyfind::{min_distance_to_oxygen, max_time_to_oxygenation};
+use rasterimg::{save_animation, ExportOptions, Format};
+
+use oxyfind::{explore, tile_palette, ShipMap, Spread, Strategy, min_distance_to_oxygen, max_time_to_oxygenation};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -14,8 +17,35 @@ fn main() -> io::Result<()> {
     println!("min distance to oxygen: {}", min_dist);
 
     // Part 2
-    let max_time = max_time_to_oxygenation(mem).expect("no oxygen found");
+    let max_time = max_time_to_oxygenation(mem.clone()).expect("no oxygen found");
     println!("time until oxygen filled: {} m", max_time);
 
+    // Full map, saved so it can be queried without rerunning the droid
+    let forked = explore(mem.clone(), Strategy::BFS);
+    let map = &forked.map;
+    println!("{}", map);
+    write("map.txt", map.to_text())?;
+    let saved = ShipMap::from_text(BufReader::new(File::open("map.txt")?))
+        .expect("failed to read saved map");
+    let oxygen = saved.oxygen().expect("no oxygen on map");
+    let start = saved.start().expect("no start on map");
+    println!("from saved map: distance {:?}, fill time {:?} m",
+        saved.distance(start, oxygen), saved.fill_time(oxygen));
+
+    // Time-lapse of the oxygen spreading from the oxygen system
+    let mut spread = Spread::from_oxygen_system(&saved).expect("no oxygen on map");
+    let frames = spread.frames();
+    println!("oxygen spread over {} minutes", spread.minute());
+    save_animation(&frames, &ExportOptions::new(Format::GIF).palette(tile_palette()).scale(2),
+        5, "oxygen.gif")?;
+
+    // Exploration strategies side by side
+    let single = explore(mem, Strategy::DFS);
+    for (strategy, run) in [(Strategy::BFS, &forked), (Strategy::DFS, &single)].iter() {
+        println!("{:?}: {} commands, {} moves, {} droids, {} words of memory, same map: {}",
+            strategy, run.stats.commands, run.stats.moves, run.stats.droids,
+            run.stats.memory_words, run.map == *map);
+    }
+
     Ok(())
 }
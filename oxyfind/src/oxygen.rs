@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use gridmap::Point;
+use rasterimg::Raster;
+
+use crate::shipmap::{ShipMap, Tile, OXYGENATED};
+
+// Oxygen filling the ship a minute at a time: every open neighbor of a
+// cell filled in the previous minute fills in the next. Sources and walls
+// can be added between minutes; iterating yields the oxygenated cells
+// after each minute until nothing more can fill.
+#[derive(Clone, Debug)]
+pub struct Spread {
+    map: ShipMap,
+    filled: HashSet<Point>,
+    frontier: Vec<Point>,
+    minute: usize,
+}
+impl Spread {
+    pub fn new(map: &ShipMap, sources: &[Point]) -> Self {
+        let mut spread = Spread{
+            map: map.clone(),
+            filled: HashSet::new(),
+            frontier: Vec::new(),
+            minute: 0,
+        };
+        for p in sources {
+            spread.add_source(*p);
+        }
+        spread
+    }
+    pub fn from_oxygen_system(map: &ShipMap) -> Option<Self> {
+        map.oxygen().map(|p| Spread::new(map, &[p]))
+    }
+    // Returns false for cells that are closed off or already oxygenated
+    pub fn add_source(&mut self, p: Point) -> bool {
+        if !self.map.is_open(p) || !self.filled.insert(p) {
+            return false;
+        }
+        self.frontier.push(p);
+        true
+    }
+    // Walls off a cell, pushing out any oxygen in it. Returns false for
+    // points off the map.
+    pub fn add_wall(&mut self, p: Point) -> bool {
+        if !self.map.set_tile(p, Tile::WALL) {
+            return false;
+        }
+        self.filled.remove(&p);
+        self.frontier.retain(|f| *f != p);
+        true
+    }
+    pub fn map(&self) -> &ShipMap {
+        &self.map
+    }
+    pub fn minute(&self) -> usize {
+        self.minute
+    }
+    pub fn filled(&self) -> &HashSet<Point> {
+        &self.filled
+    }
+    pub fn is_done(&self) -> bool {
+        self.frontier.is_empty()
+    }
+    // Advances one minute, returning the cells that filled during it. The
+    // clock stops once nothing more fills.
+    pub fn step(&mut self) -> Vec<Point> {
+        let mut next = Vec::new();
+        for p in self.frontier.iter() {
+            for n in self.map.open_neighbors(*p) {
+                if self.filled.insert(n) {
+                    next.push(n);
+                }
+            }
+        }
+        if !next.is_empty() {
+            self.minute += 1;
+        }
+        self.frontier = next.clone();
+        next
+    }
+    // Runs until the oxygen stops spreading and returns the minute it did
+    pub fn run(&mut self) -> usize {
+        while !self.is_done() {
+            self.step();
+        }
+        self.minute
+    }
+    pub fn to_raster(&self) -> Raster {
+        let mut raster = self.map.to_raster();
+        for p in self.filled.iter() {
+            raster.set(p.x as usize, p.y as usize, OXYGENATED);
+        }
+        raster
+    }
+    // One raster per minute, starting with the sources alone and ending
+    // once the ship is full. Consumes the rest of the simulation.
+    pub fn frames(&mut self) -> Vec<Raster> {
+        let mut frames = vec![self.to_raster()];
+        while !self.step().is_empty() {
+            frames.push(self.to_raster());
+        }
+        frames
+    }
+}
+impl Iterator for Spread {
+    type Item = HashSet<Point>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.step().is_empty() {
+            None
+        } else {
+            Some(self.filled.clone())
+        }
+    }
+}
+impl fmt::Display for Spread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.map.grid().rows().enumerate() {
+            for (x, t) in row.iter().enumerate() {
+                if self.filled.contains(&Point::from((x, y))) {
+                    write!(f, "O")?;
+                } else {
+                    write!(f, "{}", t.to_char())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Spread;
+    use crate::shipmap::{ShipMap, OXYGENATED};
+    use gridmap::Point;
+
+    // Fills in 4 minutes from the oxygen system
+    const EXAMPLE: &[u8] = b" ##   \n#..## \n#.#..#\n#.O.# \n ###  \n";
+
+    #[test]
+    fn fills_like_the_map_query() {
+        let map = ShipMap::from_text(EXAMPLE).unwrap();
+        let mut spread = Spread::from_oxygen_system(&map).unwrap();
+        assert_eq!(1, spread.filled().len());
+        let minutes: Vec<usize> = spread.by_ref().map(|filled| filled.len()).collect();
+        assert_eq!(vec![3, 5, 7, 8], minutes);
+        assert_eq!(4, spread.minute());
+        assert_eq!(map.fill_time(map.oxygen().unwrap()), Some(spread.minute() as u64));
+        assert_eq!(" ##   \n#OO## \n#O#OO#\n#OOO# \n ###  \n", spread.to_string());
+    }
+
+    #[test]
+    fn several_sources() {
+        let map = ShipMap::from_text(EXAMPLE).unwrap();
+        let mut spread = Spread::new(&map, &[Point::new(2, 3), Point::new(2, 1)]);
+        assert!(!spread.add_source(Point::new(0, 0)));
+        assert!(!spread.add_source(Point::new(2, 1)));
+        assert_eq!(3, spread.run());
+    }
+
+    #[test]
+    fn walls_added_mid_spread() {
+        let map = ShipMap::from_text(EXAMPLE).unwrap();
+        let mut spread = Spread::from_oxygen_system(&map).unwrap();
+        spread.step();
+        // Seals off the left-hand passage, oxygen already in it included
+        assert!(spread.add_wall(Point::new(1, 3)));
+        assert!(!spread.add_wall(Point::new(9, 9)));
+        assert_eq!(3, spread.run());
+        assert!(!spread.filled().contains(&Point::new(1, 2)));
+
+        let mut spread = Spread::from_oxygen_system(&map).unwrap();
+        let frames = spread.frames();
+        assert_eq!(5, frames.len());
+        assert_eq!(OXYGENATED, frames[4].get(2, 1));
+        assert_eq!(2, frames[0].get(2, 1));
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+
+use graphsearch::{bfs, bfs_distances, Path};
+use gridmap::{Grid, GridError, Point};
+use rasterimg::{Palette, Raster, Rgb};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Tile {
+    UNKNOWN,
+    WALL,
+    OPEN,
+    OXYGEN,
+}
+impl Tile {
+    pub fn from_char(c: char) -> Option<Tile> {
+        match c {
+            ' ' => Some(Tile::UNKNOWN),
+            '#' => Some(Tile::WALL),
+            '.' => Some(Tile::OPEN),
+            'O' => Some(Tile::OXYGEN),
+            _ => None,
+        }
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::UNKNOWN => ' ',
+            Tile::WALL => '#',
+            Tile::OPEN => '.',
+            Tile::OXYGEN => 'O',
+        }
+    }
+    pub fn is_open(self) -> bool {
+        self == Tile::OPEN || self == Tile::OXYGEN
+    }
+    pub fn raster_value(self) -> u32 {
+        match self {
+            Tile::UNKNOWN => 0,
+            Tile::WALL => 1,
+            Tile::OPEN => 2,
+            Tile::OXYGEN => 3,
+        }
+    }
+}
+
+// Raster value for cells oxygen has spread to, past the plain tiles
+pub const OXYGENATED: u32 = 4;
+
+// Colors indexed by `Tile::raster_value`, plus `OXYGENATED`
+pub fn tile_palette() -> Palette {
+    Palette::new(vec![
+        Rgb::new(0, 0, 0),
+        Rgb::new(110, 110, 110),
+        Rgb::new(235, 235, 235),
+        Rgb::new(30, 80, 220),
+        Rgb::new(130, 190, 250),
+    ])
+}
+
+// Marks the droid's starting cell in text maps; the cell itself is open
+const START: char = 'D';
+
+// Everything the droid found out about the ship, with the droid's starting
+// point and the oxygen system in grid coordinates.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ShipMap {
+    grid: Grid<Tile>,
+    start: Option<Point>,
+    oxygen: Option<Point>,
+}
+impl ShipMap {
+    pub fn new(grid: Grid<Tile>, start: Option<Point>) -> Self {
+        let oxygen = grid.position(|t| *t == Tile::OXYGEN);
+        ShipMap{ grid, start, oxygen }
+    }
+    // Reads the format written by `to_text`
+    pub fn from_text<R: BufRead>(data: R) -> Result<Self, GridError> {
+        let chars = Grid::from_reader(data)?;
+        let start = chars.position(|c| *c == START);
+        let mut rows = Vec::with_capacity(chars.height());
+        for (line, row) in chars.rows().enumerate() {
+            let tiles = row.iter().enumerate()
+                .map(|(col, &ch)| match ch {
+                    START => Ok(Tile::OPEN),
+                    _ => Tile::from_char(ch).ok_or(GridError::SYMBOL{ line, col, ch }),
+                })
+                .collect::<Result<Vec<Tile>, GridError>>()?;
+            rows.push(tiles);
+        }
+        Ok(ShipMap::new(Grid::from_rows(rows)?, start))
+    }
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+    pub fn grid(&self) -> &Grid<Tile> {
+        &self.grid
+    }
+    pub fn start(&self) -> Option<Point> {
+        self.start
+    }
+    pub fn oxygen(&self) -> Option<Point> {
+        self.oxygen
+    }
+    pub fn tile(&self, p: Point) -> Tile {
+        self.grid.get(p).cloned().unwrap_or(Tile::UNKNOWN)
+    }
+    // Returns false, leaving the map alone, for points off the map
+    pub fn set_tile(&mut self, p: Point, t: Tile) -> bool {
+        if !self.grid.set(p, t) {
+            return false;
+        }
+        if t == Tile::OXYGEN {
+            self.oxygen = Some(p);
+        } else if self.oxygen == Some(p) {
+            self.oxygen = self.grid.position(|t| *t == Tile::OXYGEN);
+        }
+        true
+    }
+    pub fn is_open(&self, p: Point) -> bool {
+        self.tile(p).is_open()
+    }
+    pub fn open_neighbors(&self, p: Point) -> Vec<Point> {
+        self.grid.neighbors4(p).filter(|n| self.is_open(*n)).collect()
+    }
+    pub fn shortest_path(&self, from: Point, to: Point) -> Option<Path<Point>> {
+        if !self.is_open(from) || !self.is_open(to) {
+            return None;
+        }
+        bfs(from, |p| self.open_neighbors(*p), |p| *p == to)
+    }
+    pub fn distance(&self, from: Point, to: Point) -> Option<u64> {
+        self.shortest_path(from, to).map(|path| path.cost)
+    }
+    pub fn distances_from(&self, from: Point) -> HashMap<Point, u64> {
+        if !self.is_open(from) {
+            return HashMap::new();
+        }
+        bfs_distances(from, |p| self.open_neighbors(*p))
+    }
+    // Cells reached by a flood starting at `from`, grouped by the minute
+    // they fill in; minute 0 holds `from` alone.
+    pub fn flood_timeline(&self, from: Point) -> Vec<Vec<Point>> {
+        let mut timeline: Vec<Vec<Point>> = Vec::new();
+        for (p, d) in self.distances_from(from) {
+            let d = d as usize;
+            if timeline.len() <= d {
+                timeline.resize(d + 1, Vec::new());
+            }
+            timeline[d].push(p);
+        }
+        for minute in timeline.iter_mut() {
+            minute.sort_by_key(|p| (p.y, p.x));
+        }
+        timeline
+    }
+    pub fn to_raster(&self) -> Raster {
+        let px = self.grid.iter().map(|(_, t)| t.raster_value()).collect();
+        Raster::from_vec(px, self.grid.width(), self.grid.height())
+    }
+    // Minutes until every cell reachable from `from` is filled
+    pub fn fill_time(&self, from: Point) -> Option<u64> {
+        self.distances_from(from).values().cloned().max()
+    }
+}
+impl fmt::Display for ShipMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.grid.rows().enumerate() {
+            for (x, t) in row.iter().enumerate() {
+                if self.start == Some(Point::from((x, y))) {
+                    write!(f, "{}", START)?;
+                } else {
+                    write!(f, "{}", t.to_char())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShipMap, Tile};
+    use gridmap::{GridError, Point};
+
+    const EXAMPLE: &[u8] =
+b" ##   
+#..## 
+#.#..#
+#.O.# 
+ ###  
+";
+
+    #[test]
+    fn text_round_trip() {
+        let map = ShipMap::from_text(&b" ## \n#D.#\n#O# \n"[..]).unwrap();
+        assert_eq!(Some(Point::new(1, 1)), map.start());
+        assert_eq!(Some(Point::new(1, 2)), map.oxygen());
+        assert_eq!(Tile::OPEN, map.tile(Point::new(1, 1)));
+        assert_eq!(Tile::UNKNOWN, map.tile(Point::new(9, 9)));
+        assert_eq!(" ## \n#D.#\n#O# \n", map.to_text());
+        assert_eq!(Err(GridError::SYMBOL{ line: 1, col: 1, ch: 'x' }),
+            ShipMap::from_text(&b"##\n.x\n"[..]));
+    }
+
+    #[test]
+    fn queries() {
+        let map = ShipMap::from_text(EXAMPLE).unwrap();
+        assert_eq!(None, map.start());
+        let oxygen = map.oxygen().unwrap();
+        let path = map.shortest_path(oxygen, Point::new(4, 2)).unwrap();
+        assert_eq!(vec![oxygen, Point::new(3, 3), Point::new(3, 2), Point::new(4, 2)],
+            path.states);
+        assert_eq!(Some(6), map.distance(Point::new(2, 1), Point::new(3, 2)));
+        assert_eq!(None, map.distance(oxygen, Point::new(0, 0)));
+        assert_eq!(Some(4), map.fill_time(oxygen));
+        let timeline = map.flood_timeline(oxygen);
+        assert_eq!(vec![oxygen], timeline[0]);
+        assert_eq!(vec![Point::new(1, 3), Point::new(3, 3)], timeline[1]);
+        assert_eq!(vec![Point::new(1, 1), Point::new(4, 2)], timeline[3]);
+        assert_eq!(vec![Point::new(2, 1)], timeline[4]);
+    }
+}
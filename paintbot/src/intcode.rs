@@ -16,6 +16,20 @@ pub struct Resources {
     input: VecDeque<isize>,
     output: VecDeque<isize>,
     relative_base: isize,
+    // Memory writes with the values they replaced, kept once a checkpoint
+    // has been taken
+    journal: Option<Vec<(usize, isize)>>,
+}
+
+// Registers and queues at some moment; memory is put back from the journal
+#[derive(Clone)]
+pub struct Checkpoint {
+    cursor: usize,
+    status: Status,
+    input: VecDeque<isize>,
+    output: VecDeque<isize>,
+    relative_base: isize,
+    writes: usize,
 }
 impl Resources {
     pub fn new(mut mem: Vec<isize>) -> Resources {
@@ -27,6 +41,7 @@ impl Resources {
             input: VecDeque::new(),
             output: VecDeque::new(),
             relative_base: 0,
+            journal: None,
         }
     }
     pub fn relative_base(&self) -> isize {
@@ -78,8 +93,36 @@ impl Resources {
         self.mem[loc as usize]
     }
     pub fn write_mem(&mut self, loc: isize, val: isize) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push((loc as usize, self.mem[loc as usize]));
+        }
         self.mem[loc as usize] = val;
     }
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let writes = self.journal.get_or_insert_with(Vec::new).len();
+        Checkpoint{
+            cursor: self.cursor,
+            status: self.status,
+            input: self.input.clone(),
+            output: self.output.clone(),
+            relative_base: self.relative_base,
+            writes,
+        }
+    }
+    // Undoes everything since the checkpoint was taken. Checkpoints taken
+    // after it are no longer valid.
+    pub fn rollback(&mut self, cp: &Checkpoint) {
+        let journal = self.journal.as_mut().expect("precondition failed, no checkpoint taken");
+        while journal.len() > cp.writes {
+            let (loc, val) = journal.pop().unwrap();
+            self.mem[loc] = val;
+        }
+        self.cursor = cp.cursor;
+        self.status = cp.status;
+        self.input = cp.input.clone();
+        self.output = cp.output.clone();
+        self.relative_base = cp.relative_base;
+    }
 }
 
 enum Mode {
@@ -337,4 +380,22 @@ mod tests {
         processor.execute(&mut res);
         assert_eq!(1125899906842624, res.read_output().unwrap());
     }
+
+    #[test]
+    fn rollback() {
+        let processor = Processor::new_intcode();
+        // Adds each input to a running total and outputs it
+        let mut res = Resources::new(vec![3,100,1,100,101,101,4,101,1105,1,0]);
+        res.write_input(5);
+        processor.execute(&mut res);
+        let cp = res.checkpoint();
+        res.write_input(7);
+        processor.resume(&mut res);
+        assert_eq!(vec![5, 12], res.output.iter().cloned().collect::<Vec<_>>());
+        res.rollback(&cp);
+        assert_eq!(5, res.read_mem(101));
+        res.write_input(1);
+        processor.resume(&mut res);
+        assert_eq!(vec![5, 6], res.output.iter().cloned().collect::<Vec<_>>());
+    }
 }
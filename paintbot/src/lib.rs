@@ -1,23 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
+use gridrobot::{Orientation, Turn};
+use rasterimg::{ocr, Palette, Raster};
+pub use gridrobot::{Heading, Point};
+pub use rasterimg::BoundingBox;
+
 mod intcode;
 use intcode::{Processor, Resources};
 
-type Color = isize;
+mod sim;
+pub use sim::{PaintEvent, Simulation};
+
+// Colors index a rasterimg palette, so are never negative
+pub type Color = isize;
 pub const BLACK: Color = 0;
 pub const WHITE: Color = 1;
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
-pub struct Point {
-    x: i32,
-    y: i32,
+fn checked(c: Color) -> Color {
+    assert!(c >= 0, "precondition failed, negative color {}", c);
+    c
 }
-impl Point {
-    pub fn new(x: i32, y: i32) -> Self {
-        Point{ x: x, y: y, }
+
+// How a color shows in text: black and white as usual, the rest as a
+// base-36 digit, or '?' past that
+fn color_char(c: Color) -> char {
+    match c {
+        BLACK => '.',
+        WHITE => '#',
+        _ => std::char::from_digit(c as u32, 36).unwrap_or('?'),
     }
 }
+
+#[derive(Copy, Clone)]
 struct Tile {
     c: Color,
     painted: bool,
@@ -38,6 +53,16 @@ impl Hull {
     fn tile_at_loc(&mut self, pt: &Point) -> &mut Tile {
         self.tiles.entry(*pt).or_insert(Tile::new())
     }
+    fn tile(&self, pt: &Point) -> Option<Tile> {
+        self.tiles.get(pt).copied()
+    }
+    // Puts a tile back the way `tile` found it
+    fn restore_tile(&mut self, pt: &Point, tile: Option<Tile>) {
+        match tile {
+            Some(t) => self.tiles.insert(*pt, t),
+            None => self.tiles.remove(pt),
+        };
+    }
     pub fn color_at_loc(&self, pt: &Point) -> Color {
         match self.tiles.get(pt) {
             Some(tile) => tile.c,
@@ -48,11 +73,11 @@ impl Hull {
         self.tile_at_loc(pt).c
     }
     pub fn set_color_at_loc(&mut self, pt: &Point, c: Color) {
-        self.tile_at_loc(pt).c = c;
+        self.tile_at_loc(pt).c = checked(c);
     }
     pub fn update_painted_color_at_loc(&mut self, pt: &Point, c: Color) {
         let mut tile = self.tile_at_loc(pt);
-        tile.c = c;
+        tile.c = checked(c);
         tile.painted = true;
     }
     pub fn num_tiles_painted(&self) -> i32 {
@@ -61,23 +86,54 @@ impl Hull {
             .sum()
     }
     pub fn num_white_tiles(&self) -> i32 {
+        self.num_tiles_of_color(WHITE)
+    }
+    pub fn num_tiles_of_color(&self, c: Color) -> i32 {
         self.tiles.values()
-            .map(|t| if t.c == WHITE { 1 } else { 0 })
+            .map(|t| if t.c == c { 1 } else { 0 })
             .sum()
     }
+    // Tiles of each color, over the tiles the bounding box covers
+    pub fn color_counts(&self) -> BTreeMap<Color, usize> {
+        let mut counts = BTreeMap::new();
+        for t in self.tiles.values().filter(|t| t.painted || t.c != BLACK) {
+            *counts.entry(t.c).or_insert(0) += 1;
+        }
+        counts
+    }
+    // Covers every tile that was painted or starts out white
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::around(self.tiles.iter()
+            .filter(|(_, t)| t.painted || t.c != BLACK)
+            .map(|(pt, _)| (pt.x, pt.y)))
+    }
+    pub fn registration_identifier(&self) -> String {
+        let white_tiles = self.tiles.iter()
+            .filter(|(_, t)| t.c == WHITE)
+            .map(|(pt, _)| ((pt.x, -pt.y), 1));
+        ocr::read_text(&Raster::from_points(white_tiles, 0), 1)
+    }
+    // Hull y grows upwards while raster rows grow downwards
+    pub fn to_raster(&self) -> Raster {
+        Raster::from_points(
+            self.tiles.iter().map(|(pt, t)| ((pt.x, -pt.y), t.c as u32)),
+            BLACK as u32)
+    }
+    pub fn to_ansi(&self, palette: &Palette) -> String {
+        self.to_raster().to_ansi(palette)
+    }
 }
 impl fmt::Display for Hull {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Assumes only positive coordinates
-        for col in (-10..10).rev() {
-            for row in -10..60 {
-                if WHITE == self.color_at_loc(&Point::new(row, col)) {
-                    write!(f, "#")?;
-                } else {
-                    write!(f, ".")?;
-                }
+        let bbox = match self.bounding_box() {
+            Some(bbox) => bbox,
+            None => return Ok(()),
+        };
+        for y in (bbox.min_y..=bbox.max_y).rev() {
+            for x in bbox.min_x..=bbox.max_x {
+                write!(f, "{}", color_char(self.color_at_loc(&Point::new(x, y))))?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -85,77 +141,90 @@ impl fmt::Display for Hull {
 
 const TURN_LEFT: isize = 0;
 const TURN_RIGHT: isize = 1;
-#[derive(Eq, PartialEq, Debug)]
-enum Direction {
-    LEFT,
-    UP,
-    RIGHT,
-    DOWN,
+fn turn_from_output(t: isize) -> Turn {
+    match t {
+        TURN_LEFT => Turn::LEFT,
+        TURN_RIGHT => Turn::RIGHT,
+        _ => panic!("precondition failed, invalid turn value"),
+    }
 }
-impl Direction {
-    pub fn new_from_turn(&self, t: isize) -> Self {
-        if t == TURN_LEFT {
-            match self {
-                Direction::LEFT => Direction::DOWN,
-                Direction::UP => Direction::LEFT,
-                Direction::RIGHT => Direction::UP,
-                Direction::DOWN => Direction::RIGHT,
-            }
-        } else if t == TURN_RIGHT {
-            match self {
-                Direction::LEFT => Direction::UP,
-                Direction::UP => Direction::RIGHT,
-                Direction::RIGHT => Direction::DOWN,
-                Direction::DOWN => Direction::LEFT,
-            }
-        } else {
-            panic!("precondition failed, invalid turn value");
-        }
+
+// How a robot's program numbers colors, for programs that don't use the
+// hull's numbering. Values without a mapping pass through unchanged, so a
+// negative one has no color.
+#[derive(Clone, Debug, Default)]
+pub struct ColorMap {
+    outputs: HashMap<isize, Color>,
+    inputs: HashMap<Color, isize>,
+}
+impl ColorMap {
+    pub fn new() -> Self {
+        ColorMap::default()
+    }
+    // The program paints `color` when it outputs `value`, and reads `color`
+    // as `value`. The first value mapped to a color is the one it reads as.
+    pub fn map(mut self, value: isize, color: Color) -> Self {
+        self.outputs.insert(value, checked(color));
+        self.inputs.entry(color).or_insert(value);
+        self
+    }
+    pub fn color_for(&self, value: isize) -> Option<Color> {
+        self.outputs.get(&value).copied().or(if value >= 0 { Some(value) } else { None })
+    }
+    pub fn value_for(&self, color: Color) -> isize {
+        *self.inputs.get(&color).unwrap_or(&color)
     }
 }
 
 pub struct Robot {
     proc: Processor,
     res: Resources,
-    loc: Point,
-    dir: Direction,
+    bot: gridrobot::Robot,
+    colors: ColorMap,
 }
 impl Robot {
     pub fn new(mem: Vec<isize>) -> Self {
         let mut robot = Robot {
             proc: Processor::new_intcode(),
             res: Resources::new(mem),
-            loc: Point::new(0, 0),
-            dir: Direction::UP,
+            bot: gridrobot::Robot::new(Point::new(0, 0), Heading::UP)
+                .with_orientation(Orientation::CARTESIAN),
+            colors: ColorMap::new(),
         };
         // Will suspend and return once blocked on input
         robot.proc.execute(&mut robot.res);
         robot
     }
+    pub fn with_start(mut self, pos: Point, heading: Heading) -> Self {
+        self.bot = gridrobot::Robot::new(pos, heading).with_orientation(Orientation::CARTESIAN);
+        self
+    }
+    pub fn with_colors(mut self, colors: ColorMap) -> Self {
+        self.colors = colors;
+        self
+    }
     pub fn paint_panel_move_on(&mut self, cur_color: Color) -> Option<Color> {
-        self.res.write_input(cur_color);
+        self.res.write_input(self.colors.value_for(cur_color));
         self.proc.resume(&mut self.res);
         let painted_color = self.res.read_output();
         let direction_num = self.res.read_output()?;
-        self.dir = self.dir.new_from_turn(direction_num);
-        self.move_forward();
-        painted_color
-    }
-    fn move_forward(&mut self) {
-        match self.dir {
-            Direction::LEFT => self.loc.x -= 1,
-            Direction::UP => self.loc.y += 1,
-            Direction::RIGHT => self.loc.x += 1,
-            Direction::DOWN => self.loc.y -= 1,
-        }
+        self.bot.turn_and_forward(turn_from_output(direction_num));
+        painted_color.map(|v| self.colors.color_for(v)
+            .expect("precondition failed, program painted an unmapped negative value"))
+    }
+    pub fn loc(&self) -> Point {
+        self.bot.pos()
+    }
+    pub fn heading(&self) -> Heading {
+        self.bot.heading()
     }
 }
 
 pub fn paint_hull_with_robot(hull: &mut Hull, robot: &mut Robot) {
     loop {
-        let initial_loc = robot.loc;
+        let initial_loc = robot.loc();
         let color_painted = robot.paint_panel_move_on(
-            hull.color_at_loc_mut(&robot.loc));
+            hull.color_at_loc_mut(&initial_loc));
         match color_painted {
             Some(c) => {
                 hull.update_painted_color_at_loc(&initial_loc, c);
@@ -169,20 +238,20 @@ pub fn paint_hull_with_robot(hull: &mut Hull, robot: &mut Robot) {
 mod tests {
     #[test]
     fn can_turn() {
-        use super::{Direction, TURN_LEFT, TURN_RIGHT};
-        let mut dir = Direction::LEFT;
-        dir = dir.new_from_turn(TURN_LEFT);
-        dir = dir.new_from_turn(TURN_LEFT);
-        assert_eq!(Direction::RIGHT, dir);
-        dir = dir.new_from_turn(TURN_LEFT);
-        dir = dir.new_from_turn(TURN_LEFT);
-        assert_eq!(Direction::LEFT, dir);
-        dir = dir.new_from_turn(TURN_RIGHT);
-        dir = dir.new_from_turn(TURN_RIGHT);
-        assert_eq!(Direction::RIGHT, dir);
-        dir = dir.new_from_turn(TURN_RIGHT);
-        dir = dir.new_from_turn(TURN_RIGHT);
-        assert_eq!(Direction::LEFT, dir);
+        use super::{turn_from_output, Heading, TURN_LEFT, TURN_RIGHT};
+        let mut dir = Heading::LEFT;
+        dir = dir.turned(turn_from_output(TURN_LEFT));
+        dir = dir.turned(turn_from_output(TURN_LEFT));
+        assert_eq!(Heading::RIGHT, dir);
+        dir = dir.turned(turn_from_output(TURN_LEFT));
+        dir = dir.turned(turn_from_output(TURN_LEFT));
+        assert_eq!(Heading::LEFT, dir);
+        dir = dir.turned(turn_from_output(TURN_RIGHT));
+        dir = dir.turned(turn_from_output(TURN_RIGHT));
+        assert_eq!(Heading::RIGHT, dir);
+        dir = dir.turned(turn_from_output(TURN_RIGHT));
+        dir = dir.turned(turn_from_output(TURN_RIGHT));
+        assert_eq!(Heading::LEFT, dir);
     }
 
     #[test]
@@ -195,6 +264,18 @@ mod tests {
         assert_eq!(None, robot.paint_panel_move_on(WHITE));
     }
 
+    #[test]
+    fn robot_turns_right_twice() {
+        use super::{Point, Robot, WHITE};
+        // paints, then turns right twice
+        let mut robot = Robot::new(
+            vec![3,100,4,100,104,1,3,100,4,100,104,1,99]);
+        robot.paint_panel_move_on(WHITE);
+        assert_eq!(Point::new(1, 0), robot.loc());
+        robot.paint_panel_move_on(WHITE);
+        assert_eq!(Point::new(1, -1), robot.loc());
+    }
+
     #[test]
     fn hull_works() {
         use super::{Hull, Point, BLACK, WHITE};
@@ -217,4 +298,89 @@ mod tests {
         assert_eq!(BLACK, hull.color_at_loc_mut(&Point::new(0, 0)));
         assert_eq!(2, hull.num_tiles_painted());
     }
+
+    fn paint_art(hull: &mut super::Hull, art: &[&str], x0: i32, y0: i32) {
+        use super::{Point, BLACK, WHITE};
+        for (j, row) in art.iter().enumerate() {
+            for (i, c) in row.chars().enumerate() {
+                let color = if c == '#' { WHITE } else { BLACK };
+                let pt = Point::new(x0 + i as i32, y0 - j as i32);
+                hull.update_painted_color_at_loc(&pt, color);
+            }
+        }
+    }
+
+    #[test]
+    fn hull_bounding_box_and_display() {
+        use super::{BoundingBox, Hull, Point, WHITE};
+        let mut hull = Hull::new();
+        assert_eq!(None, hull.bounding_box());
+        assert_eq!("", format!("{}", hull));
+        hull.color_at_loc_mut(&Point::new(50, 50));
+        hull.set_color_at_loc(&Point::new(0, 0), WHITE);
+        paint_art(&mut hull, &["#.", ".#"], -3, 1);
+        assert_eq!(Some(BoundingBox{ min_x: -3, min_y: 0, max_x: 0, max_y: 1 }),
+            hull.bounding_box());
+        assert_eq!("#...\n.#.#\n", format!("{}", hull));
+    }
+
+    #[test]
+    fn hull_registration_identifier() {
+        use super::Hull;
+        let mut hull = Hull::new();
+        paint_art(&mut hull, &[
+            ".#..#.###..####.",
+            ".#..#.#..#....#.",
+            ".####.###....#..",
+            ".#..#.#..#..#...",
+            ".#..#.#..#.#....",
+            ".#..#.###..####.",
+        ], 0, 0);
+        assert_eq!("HBZ", hull.registration_identifier());
+    }
+
+    #[test]
+    fn robot_maps_colors() {
+        use super::{paint_hull_with_robot, ColorMap, Hull, Point, Robot};
+        use super::{BLACK, WHITE};
+        const RED: isize = 2;
+        const BLUE: isize = 3;
+        // Paints back what it reads, turns right, then paints the next tile 0
+        let mem = vec![3,100,4,100,104,1,4,101,104,0,99];
+        let colors = ColorMap::new().map(0, RED).map(1, BLUE);
+        let mut robot = Robot::new(mem).with_colors(colors);
+        let mut hull = Hull::new();
+        hull.set_color_at_loc(&Point::new(0, 0), BLUE);
+        paint_hull_with_robot(&mut hull, &mut robot);
+        assert_eq!(BLUE, hull.color_at_loc(&Point::new(0, 0)));
+        assert_eq!(RED, hull.color_at_loc(&Point::new(1, 0)));
+        assert_eq!(vec![(RED, 1), (BLUE, 1)],
+            hull.color_counts().into_iter().collect::<Vec<_>>());
+        assert_eq!(0, hull.num_white_tiles());
+        // Colors the program has no number for pass through
+        assert_eq!(WHITE, ColorMap::new().map(0, RED).value_for(WHITE));
+        assert_eq!(Some(BLACK), ColorMap::new().color_for(BLACK));
+        assert_eq!(None, ColorMap::new().color_for(-1));
+        assert_eq!(Some(RED), ColorMap::new().map(-1, RED).color_for(-1));
+        assert_eq!("32\n", format!("{}", hull));
+    }
+
+    #[test]
+    #[should_panic(expected = "unmapped negative value")]
+    fn robot_rejects_negative_colors() {
+        use super::{Robot, BLACK};
+        let mut robot = Robot::new(vec![3,100,104,-1,104,0,99]);
+        robot.paint_panel_move_on(BLACK);
+    }
+
+    #[test]
+    fn hull_to_raster() {
+        use super::{Hull, Point, WHITE};
+        let mut hull = Hull::new();
+        hull.set_color_at_loc(&Point::new(-1, 2), WHITE);
+        hull.set_color_at_loc(&Point::new(1, 1), WHITE);
+        let raster = hull.to_raster();
+        assert_eq!((3, 2), (raster.width(), raster.height()));
+        assert_eq!(vec![1,0,0, 0,0,1], raster.pixels().to_vec());
+    }
 }
@@ -1,7 +1,8 @@
 use std::io;
 use std::fs::read_to_string;
 
-use paintbot::{paint_hull_with_robot, Robot, Hull, Point, WHITE};
+use paintbot::{paint_hull_with_robot, ColorMap, Heading, Robot, Hull, Point, Simulation, WHITE};
+use rasterimg::{ExportOptions, Format, Palette, Rgb};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -14,13 +15,38 @@ fn main() -> io::Result<()> {
     paint_hull_with_robot(&mut hull, &mut robot);
     println!("num tiles painted: {}", hull.num_tiles_painted());
 
-    let mut robot = Robot::new(mem);
+    let mut robot = Robot::new(mem.clone());
     let mut hull = Hull::new();
     hull.set_color_at_loc(&Point::new(0, 0), WHITE);
     paint_hull_with_robot(&mut hull, &mut robot);
     println!("{}", hull);
-    println!("{}", hull.num_white_tiles());
+    println!("registration identifier: {}", hull.registration_identifier());
     println!("num white tiles: {}", hull.num_white_tiles());
+    hull.to_raster().save(&ExportOptions::new(Format::PNG).scale(8), "hull.png")?;
+    let mut palette = Palette::black_white();
+    palette.set_color(WHITE as u32, Rgb::new(255, 200, 0));
+    print!("{}", hull.to_ansi(&palette));
+
+    // Two robots sharing a hull, starting back to back; the second paints
+    // in red where the first paints white. Both read either color as white.
+    const RED: isize = 2;
+    let mut sim = Simulation::new(Hull::new());
+    sim.add_robot(Robot::new(mem.clone())
+        .with_colors(ColorMap::new().map(1, RED).map(1, WHITE)));
+    sim.add_robot(Robot::new(mem)
+        .with_start(Point::new(0, -1), Heading::DOWN)
+        .with_colors(ColorMap::new().map(1, RED)));
+    let steps = sim.run();
+    let counts = sim.paint_counts();
+    let tiles = sim.last_painters().len();
+    println!("two robots: {} steps, {} tiles painted, most painted tile {} times",
+        steps, tiles, counts.values().max().unwrap_or(&0));
+    for (color, n) in sim.hull().color_counts() {
+        println!("  color {}: {} tiles, painted {} times", color, n,
+            sim.paints_by_color().get(&color).unwrap_or(&0));
+    }
+    sim.undo();
+    println!("after undo: {} steps, {} tiles painted", sim.steps(), sim.hull().num_tiles_painted());
 
     Ok(())
 }
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::intcode::Checkpoint;
+use crate::{Color, Hull, Point, Robot, Tile};
+
+// One robot painting one tile
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct PaintEvent {
+    pub step: usize,
+    pub robot: usize,
+    pub loc: Point,
+    pub color: Color,
+}
+
+// What to put back to undo a robot's turn
+struct Undo {
+    robot: usize,
+    bot: gridrobot::Robot,
+    checkpoint: Checkpoint,
+    // The tile as it was, if the robot painted rather than halted
+    painted: Option<(Point, Option<Tile>)>,
+}
+
+// Robots sharing a hull, taking turns in the order they were added. Each
+// turn the robot reads the tile under it, paints it and moves on; halted
+// robots drop out of the rotation. Every turn can be undone.
+pub struct Simulation {
+    hull: Hull,
+    robots: Vec<Robot>,
+    halted: Vec<bool>,
+    next: usize,
+    log: Vec<PaintEvent>,
+    undo: Vec<Undo>,
+}
+impl Simulation {
+    pub fn new(hull: Hull) -> Self {
+        Simulation{
+            hull,
+            robots: Vec::new(),
+            halted: Vec::new(),
+            next: 0,
+            log: Vec::new(),
+            undo: Vec::new(),
+        }
+    }
+    // Returns the robot's id
+    pub fn add_robot(&mut self, robot: Robot) -> usize {
+        self.robots.push(robot);
+        self.halted.push(false);
+        self.robots.len() - 1
+    }
+    pub fn hull(&self) -> &Hull {
+        &self.hull
+    }
+    pub fn robots(&self) -> &[Robot] {
+        &self.robots
+    }
+    pub fn is_halted(&self, robot: usize) -> bool {
+        self.halted[robot]
+    }
+    pub fn is_done(&self) -> bool {
+        self.halted.iter().all(|h| *h)
+    }
+    // Paint jobs so far
+    pub fn steps(&self) -> usize {
+        self.log.len()
+    }
+    // Turns robots until one paints, and returns what it did. None once
+    // every robot has halted.
+    pub fn step(&mut self) -> Option<PaintEvent> {
+        while !self.is_done() {
+            let id = self.next;
+            self.next = (self.next + 1) % self.robots.len();
+            if self.halted[id] {
+                continue;
+            }
+            let robot = &mut self.robots[id];
+            let loc = robot.loc();
+            let before = self.hull.tile(&loc);
+            let mut undo = Undo{
+                robot: id,
+                bot: robot.bot.clone(),
+                checkpoint: robot.res.checkpoint(),
+                painted: None,
+            };
+            match robot.paint_panel_move_on(self.hull.color_at_loc(&loc)) {
+                Some(color) => {
+                    self.hull.update_painted_color_at_loc(&loc, color);
+                    undo.painted = Some((loc, before));
+                    self.undo.push(undo);
+                    let event = PaintEvent{ step: self.log.len(), robot: id, loc, color };
+                    self.log.push(event);
+                    return Some(event);
+                },
+                None => {
+                    self.halted[id] = true;
+                    self.undo.push(undo);
+                },
+            }
+        }
+        None
+    }
+    // Runs until every robot halts, returning the steps taken
+    pub fn run(&mut self) -> usize {
+        let start = self.steps();
+        while self.step().is_some() {}
+        self.steps() - start
+    }
+    // Takes back the last paint job, and any robot halting since. Returns
+    // false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        while let Some(undo) = self.undo.pop() {
+            let robot = &mut self.robots[undo.robot];
+            robot.bot = undo.bot;
+            robot.res.rollback(&undo.checkpoint);
+            self.halted[undo.robot] = false;
+            self.next = undo.robot;
+            if let Some((loc, tile)) = undo.painted {
+                self.hull.restore_tile(&loc, tile);
+                self.log.pop();
+                return true;
+            }
+        }
+        false
+    }
+    // Every paint job, oldest first
+    pub fn history(&self) -> &[PaintEvent] {
+        &self.log
+    }
+    pub fn tile_history(&self, loc: &Point) -> Vec<PaintEvent> {
+        self.log.iter().filter(|e| e.loc == *loc).cloned().collect()
+    }
+    // Times each tile has been painted
+    pub fn paint_counts(&self) -> HashMap<Point, usize> {
+        let mut counts = HashMap::new();
+        for e in self.log.iter() {
+            *counts.entry(e.loc).or_insert(0) += 1;
+        }
+        counts
+    }
+    // Paint jobs in each color
+    pub fn paints_by_color(&self) -> BTreeMap<Color, usize> {
+        let mut counts = BTreeMap::new();
+        for e in self.log.iter() {
+            *counts.entry(e.color).or_insert(0) += 1;
+        }
+        counts
+    }
+    // Who painted each tile last
+    pub fn last_painters(&self) -> HashMap<Point, usize> {
+        self.log.iter().map(|e| (e.loc, e.robot)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Simulation;
+    use crate::{Heading, Hull, Point, Robot, BLACK, WHITE};
+
+    // Paints every tile the opposite of what it reads, then turns right;
+    // halts after `n` tiles
+    fn inverter(n: isize) -> Vec<isize> {
+        let mut mem = vec![
+            3,100, 1002,100,-1,100, 1001,100,1,100, 4,100, 104,1,
+            1001,101,1,101, 7,101,102,103, 1005,103,0, 99,
+        ];
+        mem.resize(103, 0);
+        mem[102] = n;
+        mem
+    }
+
+    #[test]
+    fn robots_take_turns() {
+        let mut sim = Simulation::new(Hull::new());
+        sim.add_robot(Robot::new(inverter(4)));
+        sim.add_robot(Robot::new(inverter(2)).with_start(Point::new(5, 0), Heading::DOWN));
+        assert_eq!(6, sim.run());
+        assert!(sim.is_done());
+        let robots: Vec<usize> = sim.history().iter().map(|e| e.robot).collect();
+        assert_eq!(vec![0, 1, 0, 1, 0, 0], robots);
+        assert_eq!(WHITE, sim.hull().color_at_loc(&Point::new(5, 0)));
+        assert_eq!(1, sim.paint_counts()[&Point::new(0, 0)]);
+        assert_eq!(Some(&1), sim.last_painters().get(&Point::new(5, 0)));
+        assert_eq!(Some(&6), sim.paints_by_color().get(&WHITE));
+    }
+
+    #[test]
+    fn undo_restores_robots_and_hull() {
+        let mut sim = Simulation::new(Hull::new());
+        sim.add_robot(Robot::new(inverter(8)));
+        assert_eq!(8, sim.run());
+        // A square loop of four, painted twice over
+        assert_eq!(BLACK, sim.hull().color_at_loc(&Point::new(0, 0)));
+        assert_eq!(2, sim.tile_history(&Point::new(0, 0)).len());
+        assert!(sim.undo());
+        assert!(!sim.is_done());
+        assert_eq!(7, sim.steps());
+        assert_eq!(WHITE, sim.hull().color_at_loc(&Point::new(0, -1)));
+        assert!(sim.undo() && sim.undo() && sim.undo() && sim.undo());
+        assert_eq!(WHITE, sim.hull().color_at_loc(&Point::new(0, 0)));
+        let redo = sim.run();
+        assert_eq!(5, redo);
+        assert_eq!(BLACK, sim.hull().color_at_loc(&Point::new(0, 0)));
+        while sim.undo() {}
+        assert_eq!(0, sim.hull().num_tiles_painted());
+        assert_eq!(Point::new(0, 0), sim.robots()[0].loc());
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::{self, Write};
+
+use crate::Rgb;
+
+// Minimal GIF encoder. Pixels go out as literal LZW codes with a clear code
+// often enough that the code width never grows, so no string table is
+// needed; files are bigger than a real encoder's but decode anywhere.
+
+// Bits per pixel index, at least 2 as the format requires
+pub fn min_code_size(colors: usize) -> u8 {
+    let mut bits = 2;
+    while (1 << bits) < colors {
+        bits += 1;
+    }
+    bits
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    nbits: u32,
+}
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter{ bytes: Vec::new(), acc: 0, nbits: 0 }
+    }
+    // GIF packs codes least significant bit first
+    fn push(&mut self, code: u32, width: u32) {
+        self.acc |= code << self.nbits;
+        self.nbits += width;
+        while self.nbits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+pub fn lzw_literal(px: &[u8], code_size: u8) -> Vec<u8> {
+    let clear = 1u32 << code_size;
+    let end = clear + 1;
+    let width = code_size as u32 + 1;
+    // The decoder adds a table entry for every code after the first one
+    // following a clear; stop short of the entry that would widen codes.
+    let run = (1usize << code_size) - 2;
+    let mut w = BitWriter::new();
+    w.push(clear, width);
+    for (i, chunk) in px.chunks(run).enumerate() {
+        if i > 0 {
+            w.push(clear, width);
+        }
+        for p in chunk {
+            w.push(*p as u32, width);
+        }
+    }
+    w.push(end, width);
+    w.finish()
+}
+
+fn sub_blocks(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 255 + 2);
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    out
+}
+
+// `colors` must hold exactly 2^code_size entries and every frame
+// `w * h` pixel indices. Frames are `delay` hundredths of a second apart
+// and loop forever.
+pub fn write_frames<W: Write>(out: &mut W, w: u16, h: u16, colors: &[Rgb], frames: &[Vec<u8>],
+        delay: u16) -> io::Result<()> {
+    let code_size = min_code_size(colors.len());
+    out.write_all(b"GIF89a")?;
+    out.write_all(&w.to_le_bytes())?;
+    out.write_all(&h.to_le_bytes())?;
+    let bits = code_size - 1;
+    out.write_all(&[0x80 | (bits << 4) | bits, 0, 0])?;
+    for c in colors {
+        out.write_all(&[c.r, c.g, c.b])?;
+    }
+    if frames.len() > 1 {
+        out.write_all(&[0x21, 0xff, 0x0b])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+    }
+    for px in frames {
+        out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        out.write_all(&delay.to_le_bytes())?;
+        out.write_all(&[0x00, 0x00])?;
+        out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        out.write_all(&w.to_le_bytes())?;
+        out.write_all(&h.to_le_bytes())?;
+        out.write_all(&[0x00, code_size])?;
+        out.write_all(&sub_blocks(&lzw_literal(px, code_size)))?;
+    }
+    out.write_all(&[0x3b])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lzw_literal, min_code_size, sub_blocks};
+
+    // Reference LZW decoder following the GIF rules for growing code widths
+    fn lzw_decode(data: &[u8], code_size: u8) -> Vec<u8> {
+        let clear = 1usize << code_size;
+        let end = clear + 1;
+        let mut width = code_size as usize + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..clear).map(|i| vec![i as u8]).collect();
+            table.push(Vec::new());
+            table.push(Vec::new());
+        };
+        reset(&mut table);
+        let (mut acc, mut nbits, mut bytes) = (0usize, 0usize, data.iter());
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        loop {
+            while nbits < width {
+                acc |= (*bytes.next().expect("ran out of data") as usize) << nbits;
+                nbits += 8;
+            }
+            let code = acc & ((1 << width) - 1);
+            acc >>= width;
+            nbits -= width;
+            if code == clear {
+                reset(&mut table);
+                width = code_size as usize + 1;
+                prev = None;
+                continue;
+            } else if code == end {
+                return out;
+            }
+            let entry = if code < table.len() {
+                table[code].clone()
+            } else {
+                let p = prev.clone().expect("bad code");
+                let mut e = p.clone();
+                e.push(p[0]);
+                e
+            };
+            if let Some(p) = prev {
+                let mut e = p;
+                e.push(entry[0]);
+                table.push(e);
+                if table.len() == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+            out.extend_from_slice(&entry);
+            prev = Some(entry);
+        }
+    }
+
+    #[test]
+    fn code_sizes() {
+        assert_eq!(2, min_code_size(1));
+        assert_eq!(2, min_code_size(4));
+        assert_eq!(3, min_code_size(5));
+        assert_eq!(8, min_code_size(256));
+    }
+
+    #[test]
+    fn literal_codes_decode() {
+        for code_size in 2..=8u8 {
+            let px: Vec<u8> = (0..1000).map(|i| ((i * 7) % (1 << code_size)) as u8).collect();
+            assert_eq!(px, lzw_decode(&lzw_literal(&px, code_size), code_size));
+        }
+        assert!(lzw_decode(&lzw_literal(&[], 2), 2).is_empty());
+    }
+
+    #[test]
+    fn blocks_are_terminated() {
+        let blocks = sub_blocks(&[7; 300]);
+        assert_eq!(300 + 3, blocks.len());
+        assert_eq!((255, 45, 0), (blocks[0], blocks[256], blocks[302]));
+    }
+}
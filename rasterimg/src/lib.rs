@@ -0,0 +1,430 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+mod gif;
+pub mod ocr;
+mod png;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb{ r, g, b }
+    }
+    pub fn is_dark(&self) -> bool {
+        // Rec. 601 luma, integer approximation
+        (299 * self.r as u32 + 587 * self.g as u32 + 114 * self.b as u32) / 1000 < 128
+    }
+}
+pub const BLACK: Rgb = Rgb::new(0, 0, 0);
+pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+const MISSING: Rgb = Rgb::new(255, 0, 255);
+
+// Maps pixel values to colors; values without an entry render as magenta.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: Vec<Rgb>,
+}
+impl Palette {
+    pub fn new(colors: Vec<Rgb>) -> Self {
+        Palette{ colors }
+    }
+    pub fn black_white() -> Self {
+        Palette::new(vec![BLACK, WHITE])
+    }
+    pub fn color(&self, v: u32) -> Rgb {
+        *self.colors.get(v as usize).unwrap_or(&MISSING)
+    }
+    pub fn set_color(&mut self, v: u32, c: Rgb) {
+        let idx = v as usize;
+        if idx >= self.colors.len() {
+            self.colors.resize(idx + 1, MISSING);
+        }
+        self.colors[idx] = c;
+    }
+}
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::black_white()
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct BoundingBox {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+impl BoundingBox {
+    pub fn around<I: IntoIterator<Item=(i32, i32)>>(pts: I) -> Option<Self> {
+        let mut bbox: Option<BoundingBox> = None;
+        for (x, y) in pts {
+            bbox = Some(match bbox {
+                None => BoundingBox{ min_x: x, min_y: y, max_x: x, max_y: y },
+                Some(b) => BoundingBox{
+                    min_x: b.min_x.min(x),
+                    min_y: b.min_y.min(y),
+                    max_x: b.max_x.max(x),
+                    max_y: b.max_y.max(y),
+                },
+            });
+        }
+        bbox
+    }
+    pub fn width(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize
+    }
+    pub fn height(&self) -> usize {
+        (self.max_y - self.min_y + 1) as usize
+    }
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+}
+
+// Row-major grid of palette indices, y growing downwards.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Raster {
+    w: usize,
+    h: usize,
+    px: Vec<u32>,
+}
+impl Raster {
+    pub fn new(w: usize, h: usize, fill: u32) -> Self {
+        Raster{ w, h, px: vec![fill; w * h] }
+    }
+    pub fn from_vec(px: Vec<u32>, w: usize, h: usize) -> Self {
+        assert_eq!(w * h, px.len(), "precondition failed, pixel count mismatch");
+        Raster{ w, h, px }
+    }
+    // Sizes the raster to the bounding box of the given points; uncovered
+    // cells are filled with `background`.
+    pub fn from_points<I>(pts: I, background: u32) -> Self
+            where I: IntoIterator<Item=((i32, i32), u32)> {
+        let pts: Vec<_> = pts.into_iter().collect();
+        let bbox = match BoundingBox::around(pts.iter().map(|(p, _)| *p)) {
+            Some(b) => b,
+            None => return Raster::new(0, 0, background),
+        };
+        let mut raster = Raster::new(bbox.width(), bbox.height(), background);
+        for ((x, y), v) in pts {
+            raster.set((x - bbox.min_x) as usize, (y - bbox.min_y) as usize, v);
+        }
+        raster
+    }
+    pub fn width(&self) -> usize {
+        self.w
+    }
+    pub fn height(&self) -> usize {
+        self.h
+    }
+    pub fn pixels(&self) -> &[u32] {
+        &self.px
+    }
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.px[y * self.w + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, v: u32) {
+        self.px[y * self.w + x] = v;
+    }
+    pub fn content_bounds(&self, background: u32) -> Option<BoundingBox> {
+        BoundingBox::around((0..self.h)
+            .flat_map(|y| (0..self.w).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get(x, y) != background)
+            .map(|(x, y)| (x as i32, y as i32)))
+    }
+    pub fn crop(&self, bbox: &BoundingBox) -> Self {
+        assert!(bbox.min_x >= 0 && bbox.min_y >= 0
+            && bbox.max_x < self.w as i32 && bbox.max_y < self.h as i32,
+            "precondition failed, crop outside the raster");
+        let mut out = Raster::new(bbox.width(), bbox.height(), 0);
+        for y in 0..bbox.height() {
+            for x in 0..bbox.width() {
+                let v = self.get(x + bbox.min_x as usize, y + bbox.min_y as usize);
+                out.set(x, y, v);
+            }
+        }
+        out
+    }
+    pub fn crop_to_content(&self, background: u32) -> Self {
+        match self.content_bounds(background) {
+            Some(bbox) => self.crop(&bbox),
+            None => Raster::new(0, 0, background),
+        }
+    }
+    pub fn scaled(&self, factor: usize) -> Self {
+        let mut out = Raster::new(self.w * factor, self.h * factor, 0);
+        for y in 0..out.h {
+            for x in 0..out.w {
+                out.set(x, y, self.get(x / factor, y / factor));
+            }
+        }
+        out
+    }
+    fn rgb_bytes(&self, palette: &Palette) -> Vec<u8> {
+        self.px.iter()
+            .map(|v| palette.color(*v))
+            .flat_map(|c| vec![c.r, c.g, c.b])
+            .collect()
+    }
+    pub fn write_png<W: Write>(&self, palette: &Palette, out: &mut W) -> io::Result<()> {
+        png::write_rgb(out, self.w, self.h, &self.rgb_bytes(palette))
+    }
+    pub fn write_ppm<W: Write>(&self, palette: &Palette, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.w, self.h)?;
+        out.write_all(&self.rgb_bytes(palette))
+    }
+    pub fn write_gif<W: Write>(&self, palette: &Palette, out: &mut W) -> io::Result<()> {
+        write_animation(std::slice::from_ref(self), palette, 0, out)
+    }
+    // PBM is bilevel, so each palette color is thresholded on its luma.
+    pub fn write_pbm<W: Write>(&self, palette: &Palette, out: &mut W) -> io::Result<()> {
+        write!(out, "P4\n{} {}\n", self.w, self.h)?;
+        for row in self.px.chunks(self.w.max(1)).take(self.h) {
+            let mut packed = vec![0u8; self.w.div_ceil(8)];
+            for (x, v) in row.iter().enumerate() {
+                if palette.color(*v).is_dark() {
+                    packed[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            out.write_all(&packed)?;
+        }
+        Ok(())
+    }
+    // Two spaces per pixel on a 24-bit background color, for terminals
+    pub fn to_ansi(&self, palette: &Palette) -> String {
+        let mut out = String::new();
+        for row in self.px.chunks(self.w.max(1)).take(self.h) {
+            let mut last = None;
+            for v in row.iter() {
+                let c = palette.color(*v);
+                if last != Some(c) {
+                    out.push_str(&format!("\x1b[48;2;{};{};{}m", c.r, c.g, c.b));
+                    last = Some(c);
+                }
+                out.push_str("  ");
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+    pub fn export<W: Write>(&self, opts: &ExportOptions, out: &mut W) -> io::Result<()> {
+        let raster = if opts.scale > 1 { self.scaled(opts.scale) } else { self.clone() };
+        match opts.format {
+            Format::PNG => raster.write_png(&opts.palette, out),
+            Format::PPM => raster.write_ppm(&opts.palette, out),
+            Format::PBM => raster.write_pbm(&opts.palette, out),
+            Format::GIF => raster.write_gif(&opts.palette, out),
+        }
+    }
+    pub fn save<P: AsRef<Path>>(&self, opts: &ExportOptions, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.export(opts, &mut out)?;
+        out.flush()
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Format {
+    PNG,
+    PPM,
+    PBM,
+    GIF,
+}
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(Format::PNG),
+            "ppm" => Some(Format::PPM),
+            "pbm" => Some(Format::PBM),
+            "gif" => Some(Format::GIF),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// Looping GIF with `delay` hundredths of a second between frames. Frames
+// must share one size and use at most 256 distinct pixel values.
+pub fn write_animation<W: Write>(frames: &[Raster], palette: &Palette, delay: u16, out: &mut W)
+        -> io::Result<()> {
+    let (w, h) = frames.first().map(|f| (f.w, f.h)).unwrap_or((0, 0));
+    if frames.iter().any(|f| f.w != w || f.h != h) {
+        return Err(invalid("frames differ in size"));
+    }
+    if w > u16::MAX as usize || h > u16::MAX as usize {
+        return Err(invalid("frame too large for GIF"));
+    }
+    let max_value = frames.iter().flat_map(|f| f.px.iter()).cloned().max().unwrap_or(0);
+    if max_value > 255 {
+        return Err(invalid("more than 256 pixel values"));
+    }
+    let colors: Vec<Rgb> = (0..1u32 << gif::min_code_size(max_value as usize + 1))
+        .map(|v| palette.color(v))
+        .collect();
+    let indices: Vec<Vec<u8>> = frames.iter()
+        .map(|f| f.px.iter().map(|v| *v as u8).collect())
+        .collect();
+    gif::write_frames(out, w as u16, h as u16, &colors, &indices, delay)
+}
+
+// Animated counterpart of `Raster::export`; only GIF can hold several frames
+pub fn export_animation<W: Write>(frames: &[Raster], opts: &ExportOptions, delay: u16, out: &mut W)
+        -> io::Result<()> {
+    if opts.format != Format::GIF {
+        return Err(invalid("animations need the GIF format"));
+    }
+    if opts.scale > 1 {
+        let scaled: Vec<Raster> = frames.iter().map(|f| f.scaled(opts.scale)).collect();
+        write_animation(&scaled, &opts.palette, delay, out)
+    } else {
+        write_animation(frames, &opts.palette, delay, out)
+    }
+}
+
+pub fn save_animation<P: AsRef<Path>>(frames: &[Raster], opts: &ExportOptions, delay: u16, path: P)
+        -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    export_animation(frames, opts, delay, &mut out)?;
+    out.flush()
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub format: Format,
+    pub palette: Palette,
+    pub scale: usize,
+}
+impl ExportOptions {
+    pub fn new(format: Format) -> Self {
+        ExportOptions{ format, palette: Palette::default(), scale: 1 }
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    pub fn scale(mut self, scale: usize) -> Self {
+        assert!(scale > 0, "precondition failed, scale must be positive");
+        self.scale = scale;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_animation, BoundingBox, ExportOptions, Format, Palette, Raster, Rgb};
+
+    #[test]
+    fn from_points_finds_bounds() {
+        let r = Raster::from_points(vec![((-2, 3), 1), ((1, 4), 2)], 0);
+        assert_eq!(4, r.width());
+        assert_eq!(2, r.height());
+        assert_eq!(vec![1,0,0,0, 0,0,0,2], r.pixels().to_vec());
+        assert_eq!(0, Raster::from_points(vec![], 0).width());
+    }
+
+    #[test]
+    fn ansi_runs_share_escapes() {
+        let r = Raster::from_vec(vec![1,1,0, 0,0,0], 3, 2);
+        let ansi = r.to_ansi(&Palette::black_white());
+        assert_eq!("\x1b[48;2;255;255;255m    \x1b[48;2;0;0;0m  \x1b[0m\n\x1b[48;2;0;0;0m      \x1b[0m\n",
+            ansi);
+    }
+
+    #[test]
+    fn crop_and_scale() {
+        let r = Raster::from_vec(vec![0,0,0, 0,1,1, 0,0,0], 3, 3);
+        let bbox = r.content_bounds(0).unwrap();
+        assert_eq!(BoundingBox{ min_x: 1, min_y: 1, max_x: 2, max_y: 1 }, bbox);
+        let cropped = r.crop_to_content(0);
+        assert_eq!(vec![1,1], cropped.pixels().to_vec());
+        let scaled = cropped.scaled(2);
+        assert_eq!((4, 2), (scaled.width(), scaled.height()));
+        assert!(scaled.pixels().iter().all(|v| *v == 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "crop outside the raster")]
+    fn crop_checks_bounds() {
+        Raster::new(3, 3, 0).crop(&BoundingBox{ min_x: -1, min_y: 0, max_x: 1, max_y: 1 });
+    }
+
+    #[test]
+    fn empty_rasters_export() {
+        for r in [Raster::from_points(vec![], 0), Raster::new(0, 3, 0), Raster::new(3, 0, 0)].iter() {
+            for format in [Format::PNG, Format::PPM, Format::PBM, Format::GIF].iter() {
+                let mut out = Vec::new();
+                r.export(&ExportOptions::new(*format).scale(2), &mut out).unwrap();
+                assert!(!out.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn palette_lookup() {
+        let mut p = Palette::black_white();
+        p.set_color(3, Rgb::new(10, 20, 30));
+        assert_eq!(Rgb::new(10, 20, 30), p.color(3));
+        assert_eq!(Rgb::new(255, 0, 255), p.color(2));
+        assert_eq!(Rgb::new(255, 0, 255), p.color(9));
+    }
+
+    #[test]
+    fn ppm_and_pbm() {
+        let r = Raster::from_vec(vec![0,1,1,0], 2, 2);
+        let mut ppm = Vec::new();
+        r.write_ppm(&Palette::black_white(), &mut ppm).unwrap();
+        assert_eq!(b"P6\n2 2\n255\n".to_vec(), ppm[..11].to_vec());
+        assert_eq!(vec![0,0,0, 255,255,255, 255,255,255, 0,0,0], ppm[11..].to_vec());
+
+        let mut pbm = Vec::new();
+        r.write_pbm(&Palette::black_white(), &mut pbm).unwrap();
+        assert_eq!(b"P4\n2 2\n\x80\x40".to_vec(), pbm);
+    }
+
+    #[test]
+    fn png_structure() {
+        let r = Raster::from_vec(vec![0,1,1,0], 2, 2);
+        let mut png = Vec::new();
+        r.export(&ExportOptions::new(Format::PNG).scale(3), &mut png).unwrap();
+        assert_eq!(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a], &png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        assert_eq!(&[0, 0, 0, 6, 0, 0, 0, 6, 8, 2], &png[16..26]);
+        assert_eq!(b"IEND", &png[png.len() - 8..png.len() - 4]);
+    }
+
+    #[test]
+    fn gif_animation() {
+        let frames = vec![Raster::from_vec(vec![0,1,1,0], 2, 2), Raster::from_vec(vec![1,0,0,1], 2, 2)];
+        let mut gif = Vec::new();
+        export_animation(&frames, &ExportOptions::new(Format::GIF), 10, &mut gif).unwrap();
+        assert_eq!(b"GIF89a", &gif[..6]);
+        assert_eq!(&[2, 0, 2, 0, 0x91], &gif[6..11]);
+        // Black and white, padded out to the four colors a 2 bit table holds
+        assert_eq!(&[0, 0, 0, 255, 255, 255, 255, 0, 255], &gif[13..22]);
+        assert_eq!(2, gif.windows(2).filter(|w| w == &[0x21, 0xf9]).count());
+        assert_eq!(Some(&0x3b), gif.last());
+
+        let odd = vec![Raster::new(2, 2, 0), Raster::new(3, 2, 0)];
+        assert!(export_animation(&odd, &ExportOptions::new(Format::GIF), 10, &mut Vec::new()).is_err());
+        assert!(export_animation(&frames, &ExportOptions::new(Format::PNG), 10, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Some(Format::PNG), Format::from_path("hull.PNG"));
+        assert_eq!(Some(Format::PBM), Format::from_path("out/msg.pbm"));
+        assert_eq!(Some(Format::GIF), Format::from_path("oxygen.gif"));
+        assert_eq!(None, Format::from_path("hull.txt"));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::Raster;
+
+pub const GLYPH_W: usize = 5;
+pub const GLYPH_H: usize = 6;
+
+// The 5x6 font drawn by the puzzle programs. Each glyph is a full 5x6
+// cell; most letters are 4 columns of ink with the last column left blank
+// as the gap to the next letter, while wide letters like Y fill all five.
+const FONT: &[(char, [&str; GLYPH_H])] = &[
+    ('A', [".##..", "#..#.", "#..#.", "####.", "#..#.", "#..#."]),
+    ('B', ["###..", "#..#.", "###..", "#..#.", "#..#.", "###.."]),
+    ('C', [".##..", "#..#.", "#....", "#....", "#..#.", ".##.."]),
+    ('E', ["####.", "#....", "###..", "#....", "#....", "####."]),
+    ('F', ["####.", "#....", "###..", "#....", "#....", "#...."]),
+    ('G', [".##..", "#..#.", "#....", "#.##.", "#..#.", ".###."]),
+    ('H', ["#..#.", "#..#.", "####.", "#..#.", "#..#.", "#..#."]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..##.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#..#.", "#.#..", "##...", "#.#..", "#.#..", "#..#."]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "####."]),
+    ('O', [".##..", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('P', ["###..", "#..#.", "#..#.", "###..", "#....", "#...."]),
+    ('R', ["###..", "#..#.", "#..#.", "###..", "#.#..", "#..#."]),
+    ('S', [".###.", "#....", "#....", ".##..", "...#.", "###.."]),
+    ('U', ["#..#.", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####.", "...#.", "..#..", ".#...", "#....", "####."]),
+];
+
+type Bitmap = Vec<Vec<bool>>;
+
+// Drops blank columns on either side so glyphs compare independent of
+// where they sit horizontally within their cell.
+fn trim_columns(bitmap: &Bitmap) -> Bitmap {
+    let w = bitmap.iter().map(|row| row.len()).max().unwrap_or(0);
+    let inked = |x: usize| bitmap.iter().any(|row| row[x]);
+    let first = (0..w).find(|x| inked(*x));
+    let last = (0..w).rev().find(|x| inked(*x));
+    match (first, last) {
+        (Some(first), Some(last)) =>
+            bitmap.iter().map(|row| row[first..=last].to_vec()).collect(),
+        _ => bitmap.iter().map(|_| Vec::new()).collect(),
+    }
+}
+
+fn glyph_bitmap(rows: &[&str; GLYPH_H]) -> Bitmap {
+    rows.iter().map(|r| r.chars().map(|c| c == '#').collect()).collect()
+}
+
+fn lookup(bitmap: &Bitmap) -> Option<char> {
+    let trimmed = trim_columns(bitmap);
+    FONT.iter()
+        .find(|(_, rows)| trim_columns(&glyph_bitmap(rows)) == trimmed)
+        .map(|(ch, _)| *ch)
+}
+
+fn bitmap_art(bitmap: &Bitmap) -> Vec<String> {
+    bitmap.iter()
+        .map(|row| row.iter().map(|b| if *b { '#' } else { '.' }).collect())
+        .collect()
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct GlyphMatch {
+    pub cell: usize,
+    // None unless the cell matched a font glyph exactly
+    pub ch: Option<char>,
+    pub best_guess: char,
+    // Fraction of the cell's pixels agreeing with `best_guess`
+    pub confidence: f64,
+    pub art: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct OcrReport {
+    pub text: String,
+    pub glyphs: Vec<GlyphMatch>,
+}
+impl OcrReport {
+    pub fn is_complete(&self) -> bool {
+        self.glyphs.iter().all(|g| g.ch.is_some())
+    }
+    pub fn unknown_glyphs(&self) -> Vec<&GlyphMatch> {
+        self.glyphs.iter().filter(|g| g.ch.is_none()).collect()
+    }
+    pub fn confidence(&self) -> f64 {
+        self.glyphs.iter().map(|g| g.confidence).fold(1.0, f64::min)
+    }
+}
+
+fn match_cell(cell: usize, bitmap: Bitmap) -> GlyphMatch {
+    if bitmap.iter().all(|row| row.iter().all(|b| !*b)) {
+        return GlyphMatch{ cell, ch: Some(' '), best_guess: ' ', confidence: 1.0,
+            art: bitmap_art(&bitmap) };
+    }
+    let total = GLYPH_W * GLYPH_H;
+    let (best_guess, agree) = FONT.iter()
+        .map(|(ch, rows)| {
+            let glyph = glyph_bitmap(rows);
+            let agree = (0..GLYPH_H)
+                .flat_map(|y| (0..GLYPH_W).map(move |x| (x, y)))
+                .filter(|&(x, y)| glyph[y][x] == bitmap[y][x])
+                .count();
+            (*ch, agree)
+        })
+        .fold((' ', 0), |best, cand| if cand.1 > best.1 { cand } else { best });
+    GlyphMatch{
+        cell,
+        ch: if agree == total { Some(best_guess) } else { None },
+        best_guess,
+        confidence: agree as f64 / total as f64,
+        art: bitmap_art(&bitmap),
+    }
+}
+
+// Reads letters laid out in 5x6 cells side by side from the left edge, as
+// in rendered space images, so the gap column belongs to the cell before it. Cells that do not match the
+// font exactly show up as '?' in the text and are listed in the report.
+pub fn read_cells(raster: &Raster, ink: u32) -> OcrReport {
+    let mask = ink_bitmap(raster, ink);
+    let top = mask.iter().position(|row| row.iter().any(|b| *b)).unwrap_or(0);
+    let cell_at = |x0: usize| -> Bitmap {
+        (top..top + GLYPH_H)
+            .map(|y| (x0..x0 + GLYPH_W)
+                .map(|x| mask.get(y).and_then(|row| row.get(x)).cloned().unwrap_or(false))
+                .collect())
+            .collect()
+    };
+    let glyphs: Vec<GlyphMatch> = (0..raster.width().div_ceil(GLYPH_W))
+        .map(|i| match_cell(i, cell_at(i * GLYPH_W)))
+        .collect();
+    let text = glyphs.iter()
+        .map(|g| g.ch.unwrap_or('?'))
+        .collect::<String>()
+        .trim()
+        .to_string();
+    OcrReport{ text, glyphs }
+}
+
+fn ink_bitmap(raster: &Raster, ink: u32) -> Bitmap {
+    (0..raster.height())
+        .map(|y| (0..raster.width()).map(|x| raster.get(x, y) == ink).collect())
+        .collect()
+}
+
+// Reads a line of block letters drawn with `ink`. The text is located by
+// its bounding box and split into letters on blank columns; unrecognized
+// letters come back as '?'.
+pub fn read_text(raster: &Raster, ink: u32) -> String {
+    let mask = ink_bitmap(raster, ink);
+    let top = match mask.iter().position(|row| row.iter().any(|b| *b)) {
+        Some(top) => top,
+        None => return String::new(),
+    };
+    let rows: Bitmap = mask.into_iter().skip(top).take(GLYPH_H).collect();
+    let w = rows[0].len();
+    let blank = |x: usize| rows.iter().all(|row| !row[x]);
+
+    let mut text = String::new();
+    let mut x = 0;
+    while x < w {
+        if blank(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < w && !blank(x) {
+            x += 1;
+        }
+        let glyph: Bitmap = rows.iter().map(|row| row[start..x].to_vec()).collect();
+        text.push(lookup(&glyph).unwrap_or('?'));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_cells, read_text, FONT, GLYPH_W};
+    use crate::Raster;
+
+    fn raster_from_art(art: &[&str]) -> Raster {
+        let w = art[0].len();
+        let px = art.iter()
+            .flat_map(|row| row.chars().map(|c| if c == '#' { 1 } else { 0 }))
+            .collect();
+        Raster::from_vec(px, w, art.len())
+    }
+
+    #[test]
+    fn reads_letters() {
+        let r = raster_from_art(&[
+            "........................",
+            "..####.#..#.###...##....",
+            "..#....#..#.#..#.#..#...",
+            "..###..####.###..#......",
+            "..#....#..#.#..#.#.##...",
+            "..#....#..#.#..#.#..#...",
+            "..####.#..#.###...###...",
+        ]);
+        assert_eq!("EHBG", read_text(&r, 1));
+    }
+
+    #[test]
+    fn unknown_and_empty() {
+        let r = raster_from_art(&[
+            "#..#.#",
+            "#..#.#",
+            "####.#",
+            "#..#.#",
+            "#..#.#",
+            "#..#.#",
+        ]);
+        assert_eq!("H?", read_text(&r, 1));
+        assert_eq!("", read_text(&Raster::new(5, 6, 0), 1));
+    }
+
+    #[test]
+    fn font_round_trips() {
+        for (ch, rows) in FONT {
+            assert!(rows.iter().all(|r| r.len() == GLYPH_W), "{} is not 5 wide", ch);
+            let r = raster_from_art(rows);
+            assert_eq!(ch.to_string(), read_text(&r, 1));
+        }
+    }
+
+    #[test]
+    fn reads_fixed_cells_with_report() {
+        let r = raster_from_art(&[
+            "#...#.##..#..#.",
+            "#...##..#.#..#.",
+            ".#.#.#....####.",
+            "..#..#.##.#..#.",
+            "..#..#..#.#.##.",
+            "..#...###.#..#.",
+        ]);
+        let report = read_cells(&r, 1);
+        assert_eq!("YG?", report.text);
+        assert!(!report.is_complete());
+        let unknown = report.unknown_glyphs();
+        assert_eq!(1, unknown.len());
+        assert_eq!(2, unknown[0].cell);
+        assert_eq!('H', unknown[0].best_guess);
+        assert_eq!(29.0 / 30.0, unknown[0].confidence);
+        assert_eq!("#.##.", unknown[0].art[4]);
+        assert_eq!(29.0 / 30.0, report.confidence());
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+
+// Minimal truecolor PNG encoder. Image data is wrapped in uncompressed
+// (stored) deflate blocks, which keeps the encoder tiny at the cost of size.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    table
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let table = crc32_table();
+    let mut c = 0xffff_ffffu32;
+    for b in bytes {
+        c = table[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    c ^ 0xffff_ffff
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for x in bytes {
+        a = (a + *x as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut body = Vec::with_capacity(data.len() + 4);
+    body.extend_from_slice(kind);
+    body.extend_from_slice(data);
+    out.write_all(&body)?;
+    out.write_all(&crc32(&body).to_be_bytes())
+}
+
+// `rgb` holds `w * h` pixels as consecutive r, g, b bytes, row by row.
+pub fn write_rgb<W: Write>(out: &mut W, w: usize, h: usize, rgb: &[u8]) -> io::Result<()> {
+    assert_eq!(w * h * 3, rgb.len(), "precondition failed, pixel data size mismatch");
+    out.write_all(&SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(w as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(h as u32).to_be_bytes());
+    // bit depth 8, color type 2 (truecolor), default compression/filter, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &ihdr)?;
+
+    let mut scanlines = Vec::with_capacity(h * (w * 3 + 1));
+    // Rows are indexed rather than chunked so an empty image still works
+    for y in 0..h {
+        scanlines.push(0);
+        scanlines.extend_from_slice(&rgb[y * w * 3..(y + 1) * w * 3]);
+    }
+    write_chunk(out, b"IDAT", &zlib_stored(&scanlines))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, crc32, zlib_stored};
+
+    #[test]
+    fn checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0xae42_6082, crc32(b"IEND"));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn stored_blocks_split() {
+        let data = vec![7u8; 70000];
+        let z = zlib_stored(&data);
+        // header + two block headers + data + adler
+        assert_eq!(2 + 5 + 5 + 70000 + 4, z.len());
+        assert_eq!(0, z[2]);
+        assert_eq!(1, z[2 + 5 + 0xffff]);
+    }
+}
@@ -1,4 +1,70 @@
 use std::char;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+use rasterimg::Raster;
+use rasterimg::ocr::{self, OcrReport};
+
+pub const BLACK: u32 = 0;
+pub const WHITE: u32 = 1;
+pub const TRANSPARENT: u32 = 2;
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum ImageError {
+    EMPTY,
+    DIMENSIONS { w: u32, h: u32 },
+    LENGTH { len: usize, layer_size: usize },
+    CHARACTER { pos: usize, ch: char },
+    VALUE { pos: usize, value: u32 },
+    MISMATCH { layer: usize },
+}
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::EMPTY => write!(f, "image has no layers"),
+            ImageError::DIMENSIONS{ w, h } => write!(f, "invalid dimensions {}x{}", w, h),
+            ImageError::LENGTH{ len, layer_size } => write!(f,
+                "{} pixels is not a whole number of {}-pixel layers", len, layer_size),
+            ImageError::CHARACTER{ pos, ch } => write!(f,
+                "unexpected character {:?} at position {}", ch, pos),
+            ImageError::VALUE{ pos, value } => write!(f,
+                "pixel {} has value {}, which is not a single digit", pos, value),
+            ImageError::MISMATCH{ layer } => write!(f,
+                "layer {} does not match the image dimensions", layer),
+        }
+    }
+}
+impl error::Error for ImageError {}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct LayerStats {
+    counts: BTreeMap<u32, u32>,
+}
+impl LayerStats {
+    fn new(layer: &[u32]) -> Self {
+        let mut counts = BTreeMap::new();
+        for px in layer {
+            *counts.entry(*px).or_insert(0) += 1;
+        }
+        LayerStats{ counts }
+    }
+    pub fn count(&self, value: u32) -> u32 {
+        *self.counts.get(&value).unwrap_or(&0)
+    }
+    pub fn distinct_values(&self) -> Vec<u32> {
+        self.counts.keys().cloned().collect()
+    }
+    // Ties go to the smallest value
+    pub fn most_common(&self) -> Option<u32> {
+        self.counts.iter()
+            .fold(None, |best: Option<(u32, u32)>, (v, c)| match best {
+                Some((_, bc)) if bc >= *c => best,
+                _ => Some((*v, *c)),
+            })
+            .map(|(v, _)| v)
+    }
+}
 
 pub struct Image {
     buf: Vec<u32>,
@@ -9,56 +75,133 @@ pub struct Image {
 }
 impl Image {
     pub fn new(buf: Vec<u32>, w: u32, h: u32) -> Image {
+        Image::try_new(buf, w, h).expect("precondition failed, invalid image")
+    }
+    pub fn try_new(buf: Vec<u32>, w: u32, h: u32) -> Result<Image, ImageError> {
         let num_px = w * h;
+        if num_px == 0 {
+            return Err(ImageError::DIMENSIONS{ w, h });
+        }
+        if buf.is_empty() {
+            return Err(ImageError::EMPTY);
+        }
+        if !buf.len().is_multiple_of(num_px as usize) {
+            return Err(ImageError::LENGTH{ len: buf.len(), layer_size: num_px as usize });
+        }
         let num_layers = buf.len() as u32 / num_px;
-        Image{
-            buf: buf,
-            w: w,
-            h: h,
+        Ok(Image{
+            buf,
+            w,
+            h,
             num_pixels: num_px,
-            num_layers: num_layers,
+            num_layers,
+        })
+    }
+    // Parses the Space Image Format: one digit per pixel, layer after layer.
+    // Surrounding whitespace is ignored.
+    pub fn parse(input: &str, w: u32, h: u32) -> Result<Image, ImageError> {
+        let buf = input.trim().chars().enumerate()
+            .map(|(pos, ch)| ch.to_digit(10).ok_or(ImageError::CHARACTER{ pos, ch }))
+            .collect::<Result<Vec<u32>, ImageError>>()?;
+        Image::try_new(buf, w, h)
+    }
+    // Stacks the rasters into layers, first raster on top
+    pub fn from_layers(layers: &[Raster]) -> Result<Image, ImageError> {
+        let first = layers.first().ok_or(ImageError::EMPTY)?;
+        let (w, h) = (first.width(), first.height());
+        let mut buf = Vec::with_capacity(w * h * layers.len());
+        for (i, layer) in layers.iter().enumerate() {
+            if layer.width() != w || layer.height() != h {
+                return Err(ImageError::MISMATCH{ layer: i });
+            }
+            buf.extend_from_slice(layer.pixels());
         }
+        Image::try_new(buf, w as u32, h as u32)
+    }
+    pub fn from_raster(raster: &Raster) -> Result<Image, ImageError> {
+        Image::from_layers(std::slice::from_ref(raster))
+    }
+    pub fn encode(&self) -> Result<String, ImageError> {
+        self.buf.iter().enumerate()
+            .map(|(pos, px)| char::from_digit(*px, 10)
+                .ok_or(ImageError::VALUE{ pos, value: *px }))
+            .collect()
     }
+    pub fn width(&self) -> u32 {
+        self.w
+    }
+    pub fn height(&self) -> u32 {
+        self.h
+    }
+    pub fn num_layers(&self) -> u32 {
+        self.num_layers
+    }
+    pub fn layer(&self, i: u32) -> &[u32] {
+        let start = (i * self.num_pixels) as usize;
+        &self.buf[start..start + self.num_pixels as usize]
+    }
+    pub fn layer_stats(&self, i: u32) -> LayerStats {
+        LayerStats::new(self.layer(i))
+    }
+    pub fn stats_per_layer(&self) -> Vec<LayerStats> {
+        (0..self.num_layers).map(|i| self.layer_stats(i)).collect()
+    }
+    // Values outside 0-9 are not counted; see `stats_per_layer`
     pub fn digit_count_per_layer(&self) -> Vec<[u32; 10]> {
         let mut output = Vec::new();
         for i in 0..self.num_layers {
             let mut arr: [u32; 10] = [0; 10];
-            for j in 0..self.num_pixels {
-                arr[self.buf[(i*self.num_pixels+j) as usize] as usize] += 1;
+            for px in self.layer(i) {
+                if let Some(count) = arr.get_mut(*px as usize) {
+                    *count += 1;
+                }
             }
             output.push(arr);
         }
         output
     }
-    pub fn render(&self) -> Vec<u32> {
-        let mut output = Vec::new();
+    // Each pixel takes the value of the topmost layer that is not transparent
+    // for it, or `background` if every layer is.
+    pub fn composite<F: Fn(u32) -> bool>(&self, is_transparent: F, background: u32) -> Vec<u32> {
+        let mut output = Vec::with_capacity(self.num_pixels as usize);
         for i in 0..self.num_pixels {
-            for l in 0..self.num_layers {
-                let px = self.buf[(l*self.num_pixels+i) as usize];
-                if px != 2 {
-                    output.push(px);
-                    break;
-                }
-            }
+            let px = (0..self.num_layers)
+                .map(|l| self.buf[(l*self.num_pixels+i) as usize])
+                .find(|px| !is_transparent(*px))
+                .unwrap_or(background);
+            output.push(px);
         }
         output
     }
-    pub fn print(&self) {
+    pub fn render(&self) -> Vec<u32> {
+        self.composite(|px| px == TRANSPARENT, TRANSPARENT)
+    }
+    pub fn to_raster(&self) -> Raster {
+        Raster::from_vec(self.render(), self.w as usize, self.h as usize)
+    }
+    pub fn read_message(&self) -> OcrReport {
+        ocr::read_cells(&self.to_raster(), WHITE)
+    }
+    // `palette[v]` is the character drawn for pixel value v; values past the
+    // end of the palette are drawn as '?'.
+    pub fn render_text(&self, palette: &[char]) -> String {
         let img = self.render();
-        for j in 0..self.h {
-            for i in 0..self.w {
-                let px = img[(j*self.w+i) as usize];
-                let c = if px == 0 { ' ' } else { char::from_digit(px, 10).unwrap() };
-                print!("{}", c);
-            }
-            println!("");
+        let mut out = String::new();
+        for row in img.chunks(self.w as usize) {
+            out.extend(row.iter().map(|px| *palette.get(*px as usize).unwrap_or(&'?')));
+            out.push('\n');
         }
+        out
+    }
+    pub fn print(&self) {
+        print!("{}", self.render_text(&[' ', '1', '2', '3', '4', '5', '6', '7', '8', '9']));
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Image;
+    use super::{Image, ImageError};
+    use rasterimg::Raster;
 
     #[test]
     fn small_example() {
@@ -77,4 +220,80 @@ mod tests {
         assert_eq!(4, rendered_img.len());
         assert_eq!(vec![0,1,1,0], rendered_img);
     }
+
+    #[test]
+    fn raster_example() {
+        let img = Image::new(vec![0,2,2,2,1,1,2,2,2,2,1,2,0,0,0,0], 2, 2);
+        let raster = img.to_raster();
+        assert_eq!((2, 2), (raster.width(), raster.height()));
+        assert_eq!(&[0,1,1,0], raster.pixels());
+    }
+
+    #[test]
+    fn read_message_example() {
+        let mut buf = vec![2; 10 * 6];
+        let letters = [
+            "#..#.####.",
+            "#..#.#....",
+            "####.###..",
+            "#..#.#....",
+            "#..#.#....",
+            "#..#.####.",
+        ];
+        buf.extend(letters.iter()
+            .flat_map(|row| row.chars().map(|c| if c == '#' { 1 } else { 0 })));
+        let img = Image::new(buf, 10, 6);
+        let report = img.read_message();
+        assert_eq!("HE", report.text);
+        assert!(report.is_complete());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(ImageError::LENGTH{ len: 5, layer_size: 4 },
+            Image::parse("01201", 2, 2).err().unwrap());
+        assert_eq!(ImageError::CHARACTER{ pos: 2, ch: 'x' },
+            Image::parse("01x1", 2, 2).err().unwrap());
+        assert_eq!(ImageError::EMPTY, Image::parse("  \n", 2, 2).err().unwrap());
+        assert_eq!(ImageError::DIMENSIONS{ w: 0, h: 2 },
+            Image::parse("01", 0, 2).err().unwrap());
+        let img = Image::parse("0120\n", 2, 2).unwrap();
+        assert_eq!(1, img.num_layers());
+    }
+
+    #[test]
+    fn large_values_and_stats() {
+        let img = Image::new(vec![12,3,3,0, 7,7,7,12], 2, 2);
+        let dcpl = img.digit_count_per_layer();
+        assert_eq!([1,0,0,2,0,0,0,0,0,0], dcpl[0]);
+        let stats = img.stats_per_layer();
+        assert_eq!(1, stats[0].count(12));
+        assert_eq!(vec![0,3,12], stats[0].distinct_values());
+        assert_eq!(Some(3), stats[0].most_common());
+        assert_eq!(Some(7), stats[1].most_common());
+        assert_eq!(&[7,7,7,12], img.layer(1));
+    }
+
+    #[test]
+    fn custom_composite() {
+        let img = Image::new(vec![5,0,9,9, 1,5,1,9], 2, 2);
+        assert_eq!(vec![1,0,1,4], img.composite(|px| px == 5 || px == 9, 4));
+        assert_eq!("?a\n??\n", img.render_text(&['a', 'b']));
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let top = Raster::from_vec(vec![2,1,2,0], 2, 2);
+        let bottom = Raster::from_vec(vec![0,0,1,1], 2, 2);
+        let img = Image::from_layers(&[top, bottom]).unwrap();
+        let sif = img.encode().unwrap();
+        assert_eq!("21200011", sif);
+        assert_eq!(vec![0,1,1,0], Image::parse(&sif, 2, 2).unwrap().render());
+
+        let wide = Raster::from_vec(vec![10,0], 2, 1);
+        assert_eq!(ImageError::VALUE{ pos: 0, value: 10 },
+            Image::from_raster(&wide).unwrap().encode().err().unwrap());
+        assert_eq!(ImageError::MISMATCH{ layer: 1 }, Image::from_layers(&[
+            Raster::new(2, 2, 0), Raster::new(1, 2, 0)]).err().unwrap());
+    }
 }
@@ -2,14 +2,12 @@ extern crate spaceimg;
 
 use std::fs::read_to_string;
 
+use rasterimg::{ExportOptions, Format};
 use spaceimg::Image;
 
 fn main() {
     let input = read_to_string("input.txt").unwrap();
-    let buf: Vec<u32> = input.trim().chars()
-        .map(|x| x.to_digit(10).expect("failed to parse input"))
-        .collect();
-    let img = Image::new(buf, 25, 6);
+    let img = Image::parse(&input, 25, 6).expect("failed to parse input");
 
     let dcpl = img.digit_count_per_layer();
     let min_zeros = dcpl.iter()
@@ -18,4 +16,15 @@ fn main() {
     println!("one digit count * two digit count: {}", min_zeros[1] * min_zeros[2]);
     println!("rendered image:");
     img.print();
+    let message = img.read_message();
+    println!("message: {}", message.text);
+    for glyph in message.unknown_glyphs() {
+        println!("unrecognized glyph {} (closest '{}', {:.0}% match):",
+            glyph.cell, glyph.best_guess, glyph.confidence * 100.0);
+        for row in glyph.art.iter() {
+            println!("  {}", row);
+        }
+    }
+    img.to_raster().save(&ExportOptions::new(Format::PNG).scale(8), "message.png")
+        .expect("failed to export image");
 }
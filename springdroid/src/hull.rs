@@ -0,0 +1,234 @@
+use std::error;
+use std::fmt;
+
+use crate::springscript::{Program, Reg, Sensors};
+
+// Tiles a jump carries the droid
+pub const JUMP: usize = 4;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Mode {
+    WALK,
+    RUN,
+}
+impl Mode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::WALK => "WALK",
+            Mode::RUN => "RUN",
+        }
+    }
+    // How many tiles ahead the droid can see
+    pub fn range(self) -> usize {
+        match self {
+            Mode::WALK => 4,
+            Mode::RUN => 9,
+        }
+    }
+    // First sensor the program reads that the mode does not provide
+    pub fn unreadable(self, program: &Program) -> Option<Reg> {
+        let sensors = program.sensors();
+        Reg::SENSORS[self.range()..].iter().copied()
+            .find(|r| sensors & (1 << r.sensor_index().unwrap()) != 0)
+    }
+}
+
+// A stretch of hull, true for ground. The droid starts on tile 0 and is
+// across once it gets past the last tile; everything beyond is ground.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Hull {
+    ground: Vec<bool>,
+}
+impl Hull {
+    pub fn new(ground: Vec<bool>) -> Self {
+        Hull{ ground }
+    }
+    // Reads '#' for ground and '.' for holes, as the droid draws them
+    pub fn parse(s: &str) -> Option<Self> {
+        s.chars()
+            .map(|c| match c {
+                '#' => Some(true),
+                '.' => Some(false),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Hull::new)
+    }
+    pub fn len(&self) -> usize {
+        self.ground.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ground.is_empty()
+    }
+    pub fn is_ground(&self, pos: usize) -> bool {
+        self.ground.get(pos).copied().unwrap_or(true)
+    }
+    pub fn holes(&self) -> usize {
+        self.ground.iter().filter(|g| !**g).count()
+    }
+    // What the droid standing on `pos` sees
+    pub fn sensors(&self, pos: usize, mode: Mode) -> Sensors {
+        (0..mode.range())
+            .filter(|i| self.is_ground(pos + 1 + i))
+            .fold(0, |mask, i| mask | 1 << i)
+    }
+    // Whether any run of steps and jumps gets across
+    pub fn is_crossable(&self) -> bool {
+        let mut reached = vec![false; self.len() + JUMP];
+        reached[0] = self.is_ground(0);
+        for pos in 0..self.len() {
+            if !reached[pos] {
+                continue;
+            }
+            for next in [pos + 1, pos + JUMP].iter() {
+                if self.is_ground(*next) {
+                    reached[*next] = true;
+                }
+            }
+        }
+        reached[self.len()..].iter().any(|r| *r)
+    }
+    // Moves the droid along the hull, letting the program decide on every
+    // tile it lands on whether to jump
+    pub fn cross(&self, program: &Program, mode: Mode) -> Result<(), Fall> {
+        let mut pos = 0;
+        let mut trail = Vec::new();
+        while pos < self.len() {
+            trail.push(pos);
+            pos += if program.eval(self.sensors(pos, mode)) { JUMP } else { 1 };
+            if !self.is_ground(pos) {
+                return Err(Fall{ hull: self.clone(), trail, hole: pos });
+            }
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for g in self.ground.iter() {
+            write!(f, "{}", if *g { '#' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+// Where a droid went down: the tiles it decided on, in order, and the hole
+// it landed in
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Fall {
+    pub hull: Hull,
+    pub trail: Vec<usize>,
+    pub hole: usize,
+}
+impl Fall {
+    // Tile of the last decision, the one that went wrong
+    pub fn from(&self) -> usize {
+        *self.trail.last().unwrap()
+    }
+    pub fn jumped(&self) -> bool {
+        self.hole - self.from() == JUMP
+    }
+}
+impl fmt::Display for Fall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.hull)?;
+        let marks: String = (0..=self.hole)
+            .map(|pos| if pos == self.from() { '@' } else if pos == self.hole { '^' } else { ' ' })
+            .collect();
+        write!(f, "{}", marks)
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum CheckError {
+    SENSOR(Reg),
+    FELL(Fall),
+}
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::SENSOR(r) => write!(f, "sensor {} is not available", r.to_char()),
+            CheckError::FELL(fall) => write!(f, "fell into the hole at {}:\n{}", fall.hole, fall),
+        }
+    }
+}
+impl error::Error for CheckError {}
+
+// Every crossable hull of `len` tiles, fewest holes first. The droid's
+// tile is always ground.
+pub fn patterns(len: usize) -> Vec<Hull> {
+    assert!(len <= 32, "too many patterns");
+    let mut hulls: Vec<Hull> = (0..1u64 << len.saturating_sub(1))
+        .map(|holes| Hull::new((0..len).map(|i| i == 0 || holes & 1 << (i - 1) == 0).collect()))
+        .filter(Hull::is_crossable)
+        .collect();
+    hulls.sort_by_key(Hull::holes);
+    hulls
+}
+
+// Runs the program over each hull in turn and stops at the first one it
+// falls through. Returns how many hulls it got across.
+pub fn check(program: &Program, mode: Mode, hulls: &[Hull]) -> Result<usize, CheckError> {
+    if let Some(r) = mode.unreadable(program) {
+        return Err(CheckError::SENSOR(r));
+    }
+    for hull in hulls {
+        hull.cross(program, mode).map_err(CheckError::FELL)?;
+    }
+    Ok(hulls.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, patterns, CheckError, Hull, Mode};
+    use crate::springscript::{compile, Program, Reg};
+
+    #[test]
+    fn hulls_round_trip() {
+        let hull = Hull::parse("#####.##.########").unwrap();
+        assert_eq!((17, 2), (hull.len(), hull.holes()));
+        assert_eq!("#####.##.########", hull.to_string());
+        assert_eq!(None, Hull::parse("##@##"));
+        // Sensor A is the tile in front of the droid
+        assert_eq!(0b0110, hull.sensors(4, Mode::WALK));
+        assert_eq!(0b1_1111_0110, hull.sensors(4, Mode::RUN));
+        assert!(hull.is_crossable());
+        assert!(!Hull::parse("#....#").unwrap().is_crossable());
+        assert!(Hull::parse("#...#").unwrap().is_crossable());
+    }
+
+    #[test]
+    fn falls_like_the_droid() {
+        // The intcode droid running this program goes down in the same hole
+        let program = Program::parse(&["NOT A J"]).unwrap();
+        let fall = Hull::parse("#####.##.########").unwrap().cross(&program, Mode::WALK).unwrap_err();
+        assert_eq!((vec![0, 1, 2, 3, 4], 8), (fall.trail.clone(), fall.hole));
+        assert!(fall.jumped());
+        assert_eq!("#####.##.########\n    @   ^", fall.to_string());
+    }
+
+    #[test]
+    fn generated_patterns() {
+        let hulls = patterns(5);
+        assert!(hulls.iter().all(|h| h.len() == 5 && h.is_ground(0) && h.is_crossable()));
+        assert_eq!(0, hulls[0].holes());
+        // Holes on both tile 1 and tile 4 leave nowhere to land
+        assert_eq!(12, hulls.len());
+        assert_eq!(1, patterns(1).len());
+    }
+
+    #[test]
+    fn checks_programs() {
+        let hulls = patterns(12);
+        let walk = compile("!(A & B & C) & D").unwrap();
+        match check(&Program::new(vec![]), Mode::WALK, &hulls) {
+            Err(CheckError::FELL(fall)) => assert_eq!(1, fall.hull.holes()),
+            other => panic!("expected a fall, got {:?}", other),
+        }
+        assert_eq!(Err(CheckError::SENSOR(Reg::H)),
+            check(&compile("D & H").unwrap(), Mode::WALK, &hulls));
+        // Four tiles of lookahead is not enough for every hull
+        assert!(check(&walk, Mode::WALK, &hulls).is_err());
+        assert_eq!(Ok(4), check(&walk, Mode::WALK, &hulls[..4]));
+    }
+}
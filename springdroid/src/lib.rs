@@ -1,6 +1,18 @@
 mod intcode;
 use intcode::{Processor, Resources};
 
+mod springscript;
+pub use springscript::{compile, compile_table, CompileError, Expr, Instruction, Op, Program, Reg, MAX_INSTRUCTIONS};
+
+mod hull;
+pub use hull::{check, patterns, CheckError, Fall, Hull, Mode, JUMP};
+
+mod report;
+pub use report::{FallReport, Frame};
+
+mod synth;
+pub use synth::{SynthError, Synthesizer};
+
 struct SpringScriptInterpreter<'a> {
     mem: &'a Vec<isize>,
     proc: Processor,
@@ -40,50 +52,98 @@ fn exec_and_dump(mem: &Vec<isize>, prgm: &Vec<&str>, mode: &str) {
     let ssi = SpringScriptInterpreter::new(mem);
     match ssi.exec(prgm, mode) {
         Ok(v) => println!("success, hull damage is: {}", v),
-        Err(e) => println!("failed, output:\n{}", e),
+        Err(e) => match FallReport::parse(&e) {
+            Some(report) => {
+                println!("failed, fell from tile {} into the hole at {}:", report.position(), report.hole());
+                for frame in report.last_moments() {
+                    println!("{}", frame);
+                }
+            },
+            None => println!("failed, output:\n{}", e),
+        },
     }
 }
 
+// Compiles `expr`, a condition for jumping, and runs it in `mode`
+pub fn exec_expression(mem: &Vec<isize>, expr: &str, mode: &str) -> Result<(), CompileError> {
+    let program = compile(expr)?;
+    println!("{} program for {}:\n{}", mode, expr, program);
+    let lines = program.lines();
+    exec_and_dump(mem, &lines.iter().map(|l| l.as_str()).collect(), mode);
+    Ok(())
+}
+
+// Finds a program for `mode` by running candidates on the droid and
+// learning from the hulls it falls on
+pub fn synthesize_prgm(mem: &Vec<isize>, mode: Mode) -> Result<Program, SynthError> {
+    let ssi = SpringScriptInterpreter::new(mem);
+    let mut synth = Synthesizer::new(mode);
+    let (program, damage) = synth.solve(|program| {
+        let lines = program.lines();
+        ssi.exec(&lines.iter().map(|l| l.as_str()).collect(), mode.as_str())
+    })?;
+    println!("{} program learned from {} hulls:\n{}success, hull damage is: {}",
+        mode.as_str(), synth.hulls().len(), program, damage);
+    Ok(program)
+}
+
+const FIRST_PRGM: &[&str] = &[
+    "NOT A T",
+    "NOT B J",
+     "OR J T",
+    "NOT C J",
+     "OR J T",
+    "NOT D J",
+    "NOT J J",
+    "AND T J",
+];
+
+const SECOND_PRGM: &[&str] = &[
+    "NOT A T",
+    "NOT B J",
+     "OR J T",
+    "NOT C J",
+     "OR J T",
+    "NOT D J",
+    "NOT J J",
+    "AND H J",
+    "AND T J",
+    "NOT A T",
+     "OR T J",
+];
+
 pub fn walk_with_first_prgm(mem: &Vec<isize>) {
-    exec_and_dump(mem, &vec![
-        "NOT A T",
-        "NOT B J",
-         "OR J T",
-        "NOT C J",
-         "OR J T",
-        "NOT D J",
-        "NOT J J",
-        "AND T J",
-    ], "WALK");
+    exec_and_dump(mem, &FIRST_PRGM.to_vec(), "WALK");
 }
 
 pub fn run_with_second_prgm(mem: &Vec<isize>) {
-    exec_and_dump(mem, &vec![
-        "NOT A T",
-        "NOT B J",
-         "OR J T",
-        "NOT C J",
-         "OR J T",
-        "NOT D J",
-        "NOT J J",
-        "AND H J",
-        "AND T J",
-        "NOT A T",
-         "OR T J",
-    ], "RUN");
+    exec_and_dump(mem, &SECOND_PRGM.to_vec(), "RUN");
+}
+
+// Runs both programs over every crossable hull of `len` tiles without
+// the intcode droid
+pub fn simulate_prgms(len: usize) {
+    let hulls = patterns(len);
+    for (prgm, mode) in [(FIRST_PRGM, Mode::WALK), (SECOND_PRGM, Mode::RUN)].iter() {
+        let program = Program::parse(prgm).unwrap();
+        match check(&program, *mode, &hulls) {
+            Ok(n) => println!("{} program crosses all {} hulls of {} tiles", mode.as_str(), n, len),
+            Err(e) => println!("{} program {}", mode.as_str(), e),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     #[test]
     fn basic_fall_in_hole() {
-        use std::io;
         use std::fs::read_to_string;
-        use super::run_with_null_prgm;
+        use super::SpringScriptInterpreter;
         let input = read_to_string("input.txt").unwrap();
         let mem: Vec<isize> = input.trim().split(',')
             .map(|x| x.parse::<isize>().expect("failed to parse input"))
             .collect();
-        run_with_null_prgm(&mem);
+        // Never jumping walks straight into the first hole
+        assert!(SpringScriptInterpreter::new(&mem).exec(&vec![], "WALK").is_err());
     }
 }
@@ -1,7 +1,7 @@
 use std::io;
 use std::fs::read_to_string;
 
-use springdroid::{walk_with_first_prgm, run_with_second_prgm};
+use springdroid::{exec_expression, simulate_prgms, synthesize_prgm, Mode, walk_with_first_prgm, run_with_second_prgm};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -15,5 +15,16 @@ fn main() -> io::Result<()> {
     // Part 2
     run_with_second_prgm(&mem);
 
+    // Same jump conditions, compiled from boolean expressions
+    exec_expression(&mem, "!(A & B & C) & D", "WALK").expect("walk condition does not compile");
+    exec_expression(&mem, "!(A & B & C) & D & (E | H)", "RUN").expect("run condition does not compile");
+
+    // Same programs against generated hulls, no intcode needed
+    simulate_prgms(17);
+
+    // Programs found by search, without writing any SpringScript
+    synthesize_prgm(&mem, Mode::WALK).expect("no walk program found");
+    synthesize_prgm(&mem, Mode::RUN).expect("no run program found");
+
     Ok(())
 }
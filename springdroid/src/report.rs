@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::hull::{Fall, Hull, Mode};
+
+// One picture from the droid's replay of its last moments. The bottom row
+// is the hull; `altitude` is 0 for the droid standing on it, counts up
+// while it is in the air and is -1 once it is down a hole.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Frame {
+    pub rows: Vec<String>,
+    pub x: usize,
+    pub altitude: isize,
+}
+impl Frame {
+    fn parse(rows: &[&str]) -> Option<Self> {
+        let hull_row = rows.len().checked_sub(1)?;
+        let (row, x) = rows.iter().enumerate()
+            .find_map(|(row, line)| line.find('@').map(|x| (row, x)))?;
+        Some(Frame{
+            rows: rows.iter().map(|r| r.to_string()).collect(),
+            x,
+            altitude: hull_row as isize - 1 - row as isize,
+        })
+    }
+    pub fn is_standing(&self) -> bool {
+        self.altitude == 0
+    }
+    // The hull with the droid taken out of the picture
+    pub fn hull(&self) -> Option<Hull> {
+        Hull::parse(&self.rows.last()?.replace('@', "."))
+    }
+}
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows.iter() {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+// What the droid prints after failing to get across: the mode it was in
+// and a frame for every tile it moved along before going down a hole
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct FallReport {
+    pub mode: Option<Mode>,
+    pub hull: Hull,
+    pub frames: Vec<Frame>,
+}
+impl FallReport {
+    // Returns None unless the output ends with the droid in a hole
+    pub fn parse(output: &str) -> Option<Self> {
+        let mode = if output.contains("Walking...") {
+            Some(Mode::WALK)
+        } else if output.contains("Running...") {
+            Some(Mode::RUN)
+        } else {
+            None
+        };
+        let (_, replay) = output.split_once("Didn't make it across:")?;
+        let lines: Vec<&str> = replay.lines().map(|l| l.trim_end()).collect();
+        let frames = lines.split(|l| l.is_empty())
+            .filter(|rows| !rows.is_empty())
+            .map(Frame::parse)
+            .collect::<Option<Vec<_>>>()?;
+        let last = frames.last()?;
+        if last.altitude >= 0 {
+            return None;
+        }
+        let hull = last.hull()?;
+        Some(FallReport{ mode, hull, frames })
+    }
+    // Where the droid ended up, which is always a hole
+    pub fn hole(&self) -> usize {
+        self.frames.last().unwrap().x
+    }
+    // Tiles the droid stood on, each one a decision whether to jump. It
+    // is drawn standing over the hole for a frame before dropping in.
+    pub fn trail(&self) -> Vec<usize> {
+        self.frames.iter()
+            .filter(|f| f.is_standing() && self.hull.is_ground(f.x))
+            .map(|f| f.x)
+            .collect()
+    }
+    // The tile where it made the decision that took it into the hole
+    pub fn position(&self) -> usize {
+        *self.trail().last().unwrap_or(&0)
+    }
+    // Frames from the last decision on
+    pub fn last_moments(&self) -> &[Frame] {
+        let from = self.frames.iter()
+            .rposition(|f| f.is_standing() && self.hull.is_ground(f.x))
+            .unwrap_or(0);
+        &self.frames[from..]
+    }
+    // Holes in the hull, by tile
+    pub fn holes(&self) -> Vec<usize> {
+        (0..self.hull.len()).filter(|pos| !self.hull.is_ground(*pos)).collect()
+    }
+    pub fn to_fall(&self) -> Fall {
+        Fall{ hull: self.hull.clone(), trail: self.trail(), hole: self.hole() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FallReport;
+    use crate::hull::{Hull, Mode};
+    use crate::springscript::Program;
+
+    // What the droid prints running NOT A J on WALK
+    const TRANSCRIPT: &str = "Input instructions:\n\nWalking...\n\n\nDidn't make it across:\n\n\
+        .................\n.................\n@................\n#####.##.########\n\n\
+        .................\n.................\n.@...............\n#####.##.########\n\n\
+        .................\n.................\n..@..............\n#####.##.########\n\n\
+        .................\n.................\n...@.............\n#####.##.########\n\n\
+        .................\n.................\n....@............\n#####.##.########\n\n\
+        .................\n.....@...........\n.................\n#####.##.########\n\n\
+        ......@..........\n.................\n.................\n#####.##.########\n\n\
+        .................\n.......@.........\n.................\n#####.##.########\n\n\
+        .................\n.................\n........@........\n#####.##.########\n\n\
+        .................\n.................\n.................\n#####.##@########\n\n";
+
+    #[test]
+    fn parses_transcript() {
+        let report = FallReport::parse(TRANSCRIPT).unwrap();
+        assert_eq!(Some(Mode::WALK), report.mode);
+        assert_eq!(Hull::parse("#####.##.########").unwrap(), report.hull);
+        assert_eq!(vec![5, 8], report.holes());
+        assert_eq!(10, report.frames.len());
+        assert_eq!(vec![0, 1, 2, 3, 4], report.trail());
+        assert_eq!((4, 8), (report.position(), report.hole()));
+        let altitudes: Vec<isize> = report.last_moments().iter().map(|f| f.altitude).collect();
+        assert_eq!(vec![0, 1, 2, 1, 0, -1], altitudes);
+        assert_eq!(".................\n.................\n.................\n#####.##@########\n",
+            report.frames[9].to_string());
+    }
+
+    #[test]
+    fn matches_simulator() {
+        let report = FallReport::parse(TRANSCRIPT).unwrap();
+        let program = Program::parse(&["NOT A J"]).unwrap();
+        assert_eq!(Err(report.to_fall()), report.hull.cross(&program, Mode::WALK));
+    }
+
+    #[test]
+    fn rejects_other_output() {
+        assert_eq!(None, FallReport::parse("Input instructions:\n\nWalking...\n\n"));
+        assert_eq!(None, FallReport::parse("Didn't make it across:\n\n..@.\n####\n"));
+        assert_eq!(None, FallReport::parse("Didn't make it across:\n\n....\n####\n"));
+    }
+}
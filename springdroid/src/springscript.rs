@@ -0,0 +1,550 @@
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+
+// The droid's memory only holds this many instructions
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub enum Reg {
+    A, B, C, D, E, F, G, H, I,
+    T,
+    J,
+}
+impl Reg {
+    pub const SENSORS: [Reg; 9] = [
+        Reg::A, Reg::B, Reg::C, Reg::D, Reg::E, Reg::F, Reg::G, Reg::H, Reg::I,
+    ];
+
+    // Sensor A sees the tile one ahead of the droid, B two ahead, and so on
+    pub fn sensor_index(self) -> Option<usize> {
+        Reg::SENSORS.iter().position(|r| *r == self)
+    }
+    pub fn is_writable(self) -> bool {
+        self == Reg::T || self == Reg::J
+    }
+    pub fn from_char(c: char) -> Option<Reg> {
+        match c {
+            'T' => Some(Reg::T),
+            'J' => Some(Reg::J),
+            'A'..='I' => Some(Reg::SENSORS[c as usize - 'A' as usize]),
+            _ => None,
+        }
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            Reg::T => 'T',
+            Reg::J => 'J',
+            _ => (b'A' + self.sensor_index().unwrap() as u8) as char,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Op {
+    AND,
+    OR,
+    NOT,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Instruction {
+    pub op: Op,
+    pub src: Reg,
+    pub dst: Reg,
+}
+impl Instruction {
+    pub fn new(op: Op, src: Reg, dst: Reg) -> Self {
+        assert!(dst.is_writable(), "precondition failed, only T and J can be written");
+        Instruction{ op, src, dst }
+    }
+    pub fn parse(line: &str) -> Option<Self> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() != 3 || words[1].len() != 1 || words[2].len() != 1 {
+            return None;
+        }
+        let op = match words[0] {
+            "AND" => Op::AND,
+            "OR" => Op::OR,
+            "NOT" => Op::NOT,
+            _ => return None,
+        };
+        let src = Reg::from_char(words[1].chars().next()?)?;
+        let dst = Reg::from_char(words[2].chars().next()?)?;
+        if dst.is_writable() { Some(Instruction{ op, src, dst }) } else { None }
+    }
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::AND => "AND",
+            Op::OR => "OR",
+            Op::NOT => "NOT",
+        };
+        write!(f, "{} {} {}", op, self.src.to_char(), self.dst.to_char())
+    }
+}
+
+// Sensor readings as a bit mask, bit 0 for A; a set bit means ground
+pub type Sensors = u16;
+
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+impl Program {
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Program{ instructions }
+    }
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Option<Self> {
+        lines.iter()
+            .map(|l| Instruction::parse(l.as_ref()))
+            .collect::<Option<Vec<_>>>()
+            .map(Program::new)
+    }
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+    pub fn lines(&self) -> Vec<String> {
+        self.instructions.iter().map(|i| i.to_string()).collect()
+    }
+    // Whether the droid jumps given these readings; T and J start out false
+    pub fn eval(&self, sensors: Sensors) -> bool {
+        let (mut t, mut j) = (false, false);
+        for inst in self.instructions.iter() {
+            let src = match inst.src {
+                Reg::T => t,
+                Reg::J => j,
+                r => sensors & (1 << r.sensor_index().unwrap()) != 0,
+            };
+            let dst = if inst.dst == Reg::T { &mut t } else { &mut j };
+            *dst = match inst.op {
+                Op::AND => src && *dst,
+                Op::OR => src || *dst,
+                Op::NOT => !src,
+            };
+        }
+        j
+    }
+    // Mask of the sensors the program reads
+    pub fn sensors(&self) -> Sensors {
+        self.instructions.iter()
+            .filter_map(|inst| inst.src.sensor_index())
+            .fold(0, |mask, i| mask | 1 << i)
+    }
+}
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for inst in self.instructions.iter() {
+            writeln!(f, "{}", inst)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum CompileError {
+    SYNTAX { pos: usize, found: Option<char> },
+    LENGTH { len: usize },
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::SYNTAX{ pos, found: Some(ch) } =>
+                write!(f, "unexpected {:?} at position {}", ch, pos),
+            CompileError::SYNTAX{ found: None, .. } =>
+                write!(f, "unexpected end of expression"),
+            CompileError::LENGTH{ len } => write!(f,
+                "program needs {} instructions, at most {} fit", len, MAX_INSTRUCTIONS),
+        }
+    }
+}
+impl error::Error for CompileError {}
+
+// Boolean expression over the sensors, written with `!`, `&`, `|`,
+// parentheses, sensor names A to I and the constants 0 and 1
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Expr {
+    CONST(bool),
+    SENSOR(usize),
+    NOT(Box<Expr>),
+    AND(Box<Expr>, Box<Expr>),
+    OR(Box<Expr>, Box<Expr>),
+}
+impl Expr {
+    pub fn parse(src: &str) -> Result<Expr, CompileError> {
+        let tokens: Vec<(usize, char)> = src.char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .collect();
+        let mut parser = Parser{ tokens, next: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            Some((pos, ch)) => Err(CompileError::SYNTAX{ pos, found: Some(ch) }),
+            None => Ok(expr),
+        }
+    }
+    pub fn eval(&self, sensors: Sensors) -> bool {
+        match self {
+            Expr::CONST(b) => *b,
+            Expr::SENSOR(i) => sensors & (1 << i) != 0,
+            Expr::NOT(e) => !e.eval(sensors),
+            Expr::AND(a, b) => a.eval(sensors) && b.eval(sensors),
+            Expr::OR(a, b) => a.eval(sensors) || b.eval(sensors),
+        }
+    }
+    // Mask of the sensors the expression reads
+    pub fn sensors(&self) -> Sensors {
+        match self {
+            Expr::CONST(_) => 0,
+            Expr::SENSOR(i) => 1 << i,
+            Expr::NOT(e) => e.sensors(),
+            Expr::AND(a, b) | Expr::OR(a, b) => a.sensors() | b.sensors(),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, char)>,
+    next: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<(usize, char)> {
+        self.tokens.get(self.next).cloned()
+    }
+    fn unexpected(&self) -> CompileError {
+        match self.peek() {
+            Some((pos, ch)) => CompileError::SYNTAX{ pos, found: Some(ch) },
+            None => CompileError::SYNTAX{ pos: self.tokens.len(), found: None },
+        }
+    }
+    fn or(&mut self) -> Result<Expr, CompileError> {
+        let mut e = self.and()?;
+        while let Some((_, '|')) = self.peek() {
+            self.next += 1;
+            e = Expr::OR(Box::new(e), Box::new(self.and()?));
+        }
+        Ok(e)
+    }
+    fn and(&mut self) -> Result<Expr, CompileError> {
+        let mut e = self.unary()?;
+        while let Some((_, '&')) = self.peek() {
+            self.next += 1;
+            e = Expr::AND(Box::new(e), Box::new(self.unary()?));
+        }
+        Ok(e)
+    }
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let (_, ch) = self.peek().ok_or_else(|| self.unexpected())?;
+        let e = match ch {
+            '!' => {
+                self.next += 1;
+                return Ok(Expr::NOT(Box::new(self.unary()?)));
+            },
+            '(' => {
+                self.next += 1;
+                let e = self.or()?;
+                if self.peek().map(|(_, c)| c) != Some(')') {
+                    return Err(self.unexpected());
+                }
+                e
+            },
+            '0' => Expr::CONST(false),
+            '1' => Expr::CONST(true),
+            'A'..='I' => Expr::SENSOR(ch as usize - 'A' as usize),
+            _ => return Err(self.unexpected()),
+        };
+        self.next += 1;
+        Ok(e)
+    }
+}
+
+// Product of sensor readings: sensors in `mask` appear, plain where
+// `value` has the bit set and negated where it does not
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+struct Term {
+    mask: Sensors,
+    value: Sensors,
+}
+impl Term {
+    fn covers(&self, sensors: Sensors) -> bool {
+        sensors & self.mask == self.value
+    }
+    fn literals(&self) -> u32 {
+        self.mask.count_ones()
+    }
+}
+
+// Sum of products for the readings in `on`, all limited to the sensors in
+// `vars`, by Quine-McCluskey: merge terms differing in one sensor until
+// nothing merges, then cover `on` with essential primes and greedy picks.
+// Readings in `dont_care` may merge into terms but need no covering.
+fn minimize(vars: Sensors, on: &[Sensors], dont_care: &[Sensors]) -> Vec<Term> {
+    let mut primes: HashSet<Term> = HashSet::new();
+    let mut current: HashSet<Term> = on.iter().chain(dont_care.iter())
+        .map(|s| Term{ mask: vars, value: *s })
+        .collect();
+    while !current.is_empty() {
+        let mut next = HashSet::new();
+        let mut merged = HashSet::new();
+        for a in current.iter() {
+            for bit in sensor_bits(a.mask & !a.value) {
+                let b = Term{ mask: a.mask, value: a.value | bit };
+                if current.contains(&b) {
+                    next.insert(Term{ mask: a.mask & !bit, value: a.value });
+                    merged.insert(*a);
+                    merged.insert(b);
+                }
+            }
+        }
+        primes.extend(current.difference(&merged));
+        current = next;
+    }
+    let mut primes: Vec<Term> = primes.into_iter().collect();
+    primes.sort_by_key(|t| (t.literals(), t.mask, t.value));
+
+    let mut uncovered: Vec<Sensors> = on.to_vec();
+    let mut cover = Vec::new();
+    for s in on {
+        let covering: Vec<&Term> = primes.iter().filter(|t| t.covers(*s)).collect();
+        if covering.len() == 1 && !cover.contains(covering[0]) {
+            cover.push(*covering[0]);
+        }
+    }
+    uncovered.retain(|s| !cover.iter().any(|t| t.covers(*s)));
+    while !uncovered.is_empty() {
+        let best = *primes.iter()
+            .max_by_key(|t| {
+                let n = uncovered.iter().filter(|s| t.covers(**s)).count();
+                (n, std::cmp::Reverse(t.literals()))
+            })
+            .unwrap();
+        uncovered.retain(|s| !best.covers(*s));
+        cover.push(best);
+    }
+    cover
+}
+
+fn sensor_bits(mask: Sensors) -> impl Iterator<Item = Sensors> {
+    (0..Reg::SENSORS.len()).map(|i| 1 << i).filter(move |bit| mask & bit != 0)
+}
+
+fn sensor_regs(mask: Sensors) -> Vec<Reg> {
+    Reg::SENSORS.iter().enumerate()
+        .filter(|(i, _)| mask & (1 << i) != 0)
+        .map(|(_, r)| *r)
+        .collect()
+}
+
+// Builds programs while tracking what is known about T and J, so loading a
+// register can skip clearing it when its value is already known.
+struct CodeGen {
+    out: Vec<Instruction>,
+    t: Option<bool>,
+    j: Option<bool>,
+}
+impl CodeGen {
+    fn new() -> Self {
+        CodeGen{ out: Vec::new(), t: Some(false), j: Some(false) }
+    }
+    fn emit(&mut self, op: Op, src: Reg, dst: Reg) {
+        self.out.push(Instruction::new(op, src, dst));
+        let known = if dst == Reg::T { &mut self.t } else { &mut self.j };
+        *known = None;
+    }
+    fn known(&self, r: Reg) -> Option<bool> {
+        if r == Reg::T { self.t } else { self.j }
+    }
+    // dst = src
+    fn load(&mut self, src: Reg, dst: Reg) {
+        match self.known(dst) {
+            Some(false) => self.emit(Op::OR, src, dst),
+            Some(true) => self.emit(Op::AND, src, dst),
+            None => {
+                self.emit(Op::NOT, src, dst);
+                self.emit(Op::NOT, dst, dst);
+            },
+        }
+    }
+    // dst = term; only dst is written. Negated readings go through
+    // De Morgan, since there is no spare register to hold them.
+    fn product(&mut self, term: Term, dst: Reg) {
+        let pos = sensor_regs(term.value);
+        let neg = sensor_regs(term.mask & !term.value);
+        match (neg.len(), pos.first()) {
+            (0, None) => {
+                // Always true
+                if self.known(dst) == Some(false) {
+                    self.emit(Op::NOT, dst, dst);
+                } else if self.known(dst) != Some(true) {
+                    self.emit(Op::NOT, Reg::A, dst);
+                    self.emit(Op::OR, Reg::A, dst);
+                }
+            },
+            (0, Some(first)) => self.load(*first, dst),
+            (1, _) => self.emit(Op::NOT, neg[0], dst),
+            _ => {
+                self.load(neg[0], dst);
+                for n in neg.iter().skip(1) {
+                    self.emit(Op::OR, *n, dst);
+                }
+                self.emit(Op::NOT, dst, dst);
+            },
+        }
+        let skip = if neg.is_empty() { 1 } else { 0 };
+        for p in pos.iter().skip(skip) {
+            self.emit(Op::AND, *p, dst);
+        }
+    }
+    // J = sum of the terms, built up through T after the first one
+    fn sum_of_products(mut self, terms: &[Term]) -> Self {
+        for (i, term) in terms.iter().enumerate() {
+            if i == 0 {
+                self.product(*term, Reg::J);
+            } else {
+                self.product(*term, Reg::T);
+                self.emit(Op::OR, Reg::T, Reg::J);
+            }
+        }
+        self
+    }
+}
+
+// Orders in which to try building the terms; which term lands in J, and
+// which in T while T is still known to be clear, changes the length
+fn orderings(n: usize) -> Vec<Vec<usize>> {
+    const MAX_PERMUTED: usize = 6;
+    if n > MAX_PERMUTED {
+        return vec![(0..n).collect()];
+    }
+    let mut out = vec![Vec::new()];
+    for _ in 0..n {
+        out = out.into_iter()
+            .flat_map(|prefix: Vec<usize>| (0..n)
+                .filter(|i| !prefix.contains(i))
+                .map(|i| {
+                    let mut next = prefix.clone();
+                    next.push(i);
+                    next
+                })
+                .collect::<Vec<_>>())
+            .collect();
+    }
+    out
+}
+
+fn shortest_sum_of_products(terms: &[Term]) -> Vec<Instruction> {
+    orderings(terms.len()).into_iter()
+        .map(|order| {
+            let ordered: Vec<Term> = order.iter().map(|i| terms[*i]).collect();
+            CodeGen::new().sum_of_products(&ordered).out
+        })
+        .min_by_key(|out| out.len())
+        .unwrap_or_default()
+}
+
+// Compiles `src` by its truth table over the sensors it reads
+pub fn compile(src: &str) -> Result<Program, CompileError> {
+    let expr = Expr::parse(src)?;
+    let vars = expr.sensors();
+    let readings: Vec<Sensors> = (0..=vars).filter(|s| s & !vars == 0).collect();
+    let (on, off): (Vec<Sensors>, Vec<Sensors>) = readings.iter().partition(|s| expr.eval(**s));
+    compile_table(vars, &on, &off)
+}
+
+// Compiles a partial truth table over the sensors in `vars`: the program
+// jumps for every reading in `on` and not for any in `off`, and is free to
+// do either for the rest. Picks the shorter of two forms: the minimized
+// table built directly in J, or its minimized complement followed by a
+// final NOT.
+pub fn compile_table(vars: Sensors, on: &[Sensors], off: &[Sensors]) -> Result<Program, CompileError> {
+    let dont_care: Vec<Sensors> = (0..=vars)
+        .filter(|s| s & !vars == 0 && !on.contains(s) && !off.contains(s))
+        .collect();
+    let direct = shortest_sum_of_products(&minimize(vars, on, &dont_care));
+    let mut inverted = shortest_sum_of_products(&minimize(vars, off, &dont_care));
+    inverted.push(Instruction::new(Op::NOT, Reg::J, Reg::J));
+    let best = if inverted.len() < direct.len() { inverted } else { direct };
+
+    let program = Program::new(best);
+    assert!(on.iter().all(|s| program.eval(*s)) && off.iter().all(|s| !program.eval(*s)),
+        "postcondition failed, compiled program disagrees with its truth table");
+    if program.len() > MAX_INSTRUCTIONS {
+        return Err(CompileError::LENGTH{ len: program.len() });
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile, compile_table, CompileError, Expr, Instruction, Op, Program, Reg, MAX_INSTRUCTIONS};
+
+    fn agrees(src: &str) -> Program {
+        let expr = Expr::parse(src).unwrap();
+        let program = compile(src).unwrap();
+        for s in 0..(1 << 9) {
+            assert_eq!(expr.eval(s), program.eval(s), "{} with sensors {:09b}", src, s);
+        }
+        program
+    }
+
+    #[test]
+    fn instructions_round_trip() {
+        let lines = ["NOT A T", "OR T J", "AND H J"];
+        let program = Program::parse(&lines).unwrap();
+        assert_eq!(Instruction::new(Op::AND, Reg::H, Reg::J), program.instructions()[2]);
+        assert_eq!(lines.to_vec(), program.lines());
+        assert_eq!(None, Instruction::parse("AND J A"));
+        assert_eq!(None, Instruction::parse("XOR A J"));
+        assert_eq!(None, Program::parse(&["NOT A T", "NOT K J"]));
+    }
+
+    #[test]
+    fn parses_expressions() {
+        assert_eq!(Ok(Expr::AND(Box::new(Expr::NOT(Box::new(Expr::SENSOR(0)))),
+            Box::new(Expr::SENSOR(3)))), Expr::parse("!A & D"));
+        assert_eq!(Err(CompileError::SYNTAX{ pos: 4, found: Some('K') }), Expr::parse("A & K"));
+        assert_eq!(Err(CompileError::SYNTAX{ pos: 3, found: None }), Expr::parse("(A|"));
+        assert_eq!(Err(CompileError::SYNTAX{ pos: 2, found: Some('B') }), Expr::parse("A B"));
+    }
+
+    #[test]
+    fn compiles_puzzle_programs() {
+        // Jump when there is a hole ahead and somewhere to land
+        let walk = agrees("!(A & B & C) & D");
+        assert!(walk.len() <= 6, "walk program is\n{}", walk);
+        let run = agrees("!(A & B & C) & D & (E | H)");
+        assert!(run.len() <= 10, "run program is\n{}", run);
+    }
+
+    #[test]
+    fn compiles_edge_cases() {
+        assert!(agrees("0").is_empty());
+        assert_eq!(1, agrees("1").len());
+        assert_eq!(1, agrees("!A").len());
+        assert_eq!(1, agrees("A").len());
+        agrees("A & !B & !C | !A & B & C | D & !E & F & !G");
+        agrees("(A | B) & (C | !D) & (!E | F) & (G | !H | I)");
+        // Parity has no short sum of products
+        let xor = |a: String, b: &str| format!("(({})&!{}|!({})&{})", a, b, a, b);
+        let parity = ["B", "C", "D", "E"].iter().fold("A".to_string(), |acc, v| xor(acc, v));
+        match compile(&parity) {
+            Err(CompileError::LENGTH{ len }) => assert!(len > MAX_INSTRUCTIONS),
+            other => panic!("expected LENGTH error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn free_readings_shorten_tables() {
+        // Any one sensor tells these two readings apart
+        let program = compile_table(0b1111, &[0b1000], &[0b1111]).unwrap();
+        assert_eq!(1, program.len());
+        assert!(program.eval(0b1000) && !program.eval(0b1111));
+        assert!(compile_table(0b1, &[], &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use crate::hull::{Hull, Mode, JUMP};
+use crate::report::FallReport;
+use crate::springscript::{compile_table, Program, Sensors};
+
+// Partial tables tried before giving up on the known hulls
+const SEARCH_LIMIT: usize = 100_000;
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum SynthError {
+    // No program that fits gets across every hull seen so far
+    NOPROGRAM { hulls: usize },
+    // The droid's output holds no hull to learn from
+    TRANSCRIPT(String),
+    // The droid fell on a hull the program was built to cross
+    REPEATED(Hull),
+}
+impl fmt::Display for SynthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SynthError::NOPROGRAM{ hulls } =>
+                write!(f, "no program gets across all {} hulls seen", hulls),
+            SynthError::TRANSCRIPT(out) => write!(f, "no failing hull in droid output:\n{}", out),
+            SynthError::REPEATED(hull) => write!(f, "fell again on hull {}", hull),
+        }
+    }
+}
+impl error::Error for SynthError {}
+
+// Follows the droid over the hull as far as the table says what to do.
+// Returns whether it got across, or the first reading the table lacks.
+fn replay(hull: &Hull, mode: Mode, table: &HashMap<Sensors, bool>) -> Result<bool, Sensors> {
+    let mut pos = 0;
+    while pos < hull.len() {
+        let sensors = hull.sensors(pos, mode);
+        match table.get(&sensors) {
+            Some(jump) => pos += if *jump { JUMP } else { 1 },
+            None => return Err(sensors),
+        }
+        if !hull.is_ground(pos) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Learns a jump condition from the hulls the droid has fallen on. Each
+// hull constrains the readings met while crossing it; the search fills
+// in a truth table one reading at a time until every known hull is
+// crossed, then compiles the table with the untouched readings left free.
+#[derive(Clone, Debug)]
+pub struct Synthesizer {
+    mode: Mode,
+    hulls: Vec<Hull>,
+}
+impl Synthesizer {
+    pub fn new(mode: Mode) -> Self {
+        Synthesizer{ mode, hulls: Vec::new() }
+    }
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+    pub fn hulls(&self) -> &[Hull] {
+        &self.hulls
+    }
+    // Returns false for hulls already known
+    pub fn learn(&mut self, hull: Hull) -> bool {
+        if self.hulls.contains(&hull) {
+            return false;
+        }
+        self.hulls.push(hull);
+        true
+    }
+    // A program that gets across every known hull, if one fits
+    pub fn program(&self) -> Option<Program> {
+        let mut budget = SEARCH_LIMIT;
+        self.search(&mut HashMap::new(), &mut budget)
+    }
+    fn search(&self, table: &mut HashMap<Sensors, bool>, budget: &mut usize) -> Option<Program> {
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+        let mut undecided = None;
+        for hull in self.hulls.iter() {
+            match replay(hull, self.mode, table) {
+                Ok(false) => return None,
+                Err(s) => undecided = undecided.or(Some(s)),
+                Ok(true) => (),
+            }
+        }
+        let sensors = match undecided {
+            Some(s) => s,
+            None => {
+                let (on, off): (Vec<Sensors>, Vec<Sensors>) = table.keys().partition(|s| table[*s]);
+                let vars = (1 << self.mode.range()) - 1;
+                return compile_table(vars, &on, &off).ok();
+            },
+        };
+        for jump in [false, true].iter() {
+            table.insert(sensors, *jump);
+            if let Some(program) = self.search(table, budget) {
+                return Some(program);
+            }
+        }
+        table.remove(&sensors);
+        None
+    }
+    // Alternates between building a program and running it with `run`,
+    // which returns the hull damage reported or the droid's output when it
+    // falls, until a program makes it
+    pub fn solve<F>(&mut self, mut run: F) -> Result<(Program, isize), SynthError>
+            where F: FnMut(&Program) -> Result<isize, String> {
+        loop {
+            let program = self.program()
+                .ok_or(SynthError::NOPROGRAM{ hulls: self.hulls.len() })?;
+            match run(&program) {
+                Ok(damage) => return Ok((program, damage)),
+                Err(output) => {
+                    let hull = FallReport::parse(&output)
+                        .ok_or(SynthError::TRANSCRIPT(output))?
+                        .hull;
+                    if !self.learn(hull.clone()) {
+                        return Err(SynthError::REPEATED(hull));
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SynthError, Synthesizer};
+    use crate::hull::{check, CheckError, Hull, Mode};
+    use crate::springscript::Program;
+
+    // Runs programs over `hulls` the way the droid would, drawing only the
+    // last frame when it falls
+    fn droid(mode: Mode, hulls: &[&str]) -> impl FnMut(&Program) -> Result<isize, String> {
+        let hulls: Vec<Hull> = hulls.iter().map(|h| Hull::parse(h).unwrap()).collect();
+        move |program| match check(program, mode, &hulls) {
+            Ok(n) => Ok(n as isize + 1000),
+            Err(CheckError::FELL(fall)) => {
+                let mut hull: Vec<char> = fall.hull.to_string().chars().collect();
+                hull[fall.hole] = '@';
+                Err(format!("Didn't make it across:\n\n{}\n\n", hull.iter().collect::<String>()))
+            },
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[test]
+    fn learns_walk_and_run() {
+        let walk = ["#####.###########", "#####...#########", "#####..#.########", "#####.#..########"];
+        let mut synth = Synthesizer::new(Mode::WALK);
+        let (program, damage) = synth.solve(droid(Mode::WALK, &walk)).unwrap();
+        assert_eq!(1004, damage);
+        assert!(!synth.hulls().is_empty());
+        assert!(program.len() <= 15);
+
+        let run = ["#####.#.#...#####", "#####.##.##..####", "#####...##.#.####", "#####.#.##..#.###"];
+        let mut synth = Synthesizer::new(Mode::RUN);
+        assert_eq!(1004, synth.solve(droid(Mode::RUN, &run)).unwrap().1);
+    }
+
+    #[test]
+    fn reports_dead_ends() {
+        let mut synth = Synthesizer::new(Mode::WALK);
+        let out = "Walking...\n".to_string();
+        assert_eq!(Err(SynthError::TRANSCRIPT(out.clone())), synth.solve(|_| Err(out.clone())));
+        // A droid that keeps falling on the same hull teaches nothing new
+        let mut lying = |_: &Program| Err("Didn't make it across:\n\n#####@###########\n".to_string());
+        let hull = Hull::parse("#####.###########").unwrap();
+        assert_eq!(Err(SynthError::REPEATED(hull)), synth.solve(&mut lying));
+    }
+}
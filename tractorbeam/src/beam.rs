@@ -0,0 +1,334 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+// Anything that can say whether a point is pulled by the beam. Closures
+// over (x, y) work as probes, which keeps the model testable without an
+// intcode program.
+pub trait Probe {
+    fn probe(&mut self, x: isize, y: isize) -> bool;
+}
+impl<F: FnMut(isize, isize) -> bool> Probe for F {
+    fn probe(&mut self, x: isize, y: isize) -> bool {
+        self(x, y)
+    }
+}
+
+// Non-negative fraction, kept in lowest terms
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Ratio {
+    pub num: isize,
+    pub den: isize,
+}
+impl Ratio {
+    pub fn new(num: isize, den: isize) -> Self {
+        assert!(den > 0, "precondition failed, denominator must be positive");
+        let g = gcd(num.abs(), den);
+        Ratio{ num: num / g, den: den / g }
+    }
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+    fn mediant(self, other: Ratio) -> Ratio {
+        Ratio{ num: self.num + other.num, den: self.den + other.den }
+    }
+}
+impl Ord for Ratio {
+    fn cmp(&self, other: &Ratio) -> Ordering {
+        (self.num as i128 * other.den as i128).cmp(&(other.num as i128 * self.den as i128))
+    }
+}
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Ratio) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+// Smallest-denominator fraction between `lo` and `hi`, found by walking
+// down the Stern-Brocot tree. The flags say whether each end is allowed.
+fn simplest_between(lo: Ratio, lo_closed: bool, hi: Ratio, hi_closed: bool) -> Option<Ratio> {
+    let above_lo = |m: Ratio| m > lo || (lo_closed && m == lo);
+    let below_hi = |m: Ratio| m < hi || (hi_closed && m == hi);
+    if lo > hi || (lo == hi && !(lo_closed && hi_closed)) {
+        return None;
+    }
+    let (mut left, mut right) = (Ratio{ num: 0, den: 1 }, Ratio{ num: 1, den: 0 });
+    if above_lo(left) && below_hi(left) {
+        return Some(left);
+    }
+    loop {
+        let m = left.mediant(right);
+        if !above_lo(m) {
+            left = m;
+        } else if !below_hi(m) {
+            right = m;
+        } else {
+            return Some(m);
+        }
+    }
+}
+
+// Slopes of the beam edges in columns per row, so the beam covers roughly
+// left * y <= x <= right * y
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct EdgeSlopes {
+    pub left: Ratio,
+    pub right: Ratio,
+}
+
+// Cached view of a beam fanning out from the origin into x, y >= 0. The
+// beam is assumed to be a cone, so every row holds one run of pulled cells
+// whose ends never move left from one row to the next; beyond that, its
+// angle and width are free.
+pub struct BeamModel<P: Probe> {
+    probe: P,
+    cache: HashMap<(isize, isize), bool>,
+    queries: usize,
+    // Extent of the beam on each traced row, from row 0 down
+    rows: Vec<Option<(isize, isize)>>,
+    // Last traced row with any beam on it, as (y, left, right)
+    last: Option<(isize, isize, isize)>,
+    seeded: bool,
+}
+
+// The beam is looked for in squares of growing size before giving up
+const MAX_SEED: isize = 1 << 12;
+
+impl<P: Probe> BeamModel<P> {
+    pub fn new(probe: P) -> Self {
+        BeamModel{
+            probe,
+            cache: HashMap::new(),
+            queries: 0,
+            rows: Vec::new(),
+            last: None,
+            seeded: false,
+        }
+    }
+    pub fn is_pulled(&mut self, x: isize, y: isize) -> bool {
+        self.queries += 1;
+        if x < 0 || y < 0 {
+            return false;
+        }
+        if let Some(hit) = self.cache.get(&(x, y)) {
+            return *hit;
+        }
+        let hit = self.probe.probe(x, y);
+        self.cache.insert((x, y), hit);
+        hit
+    }
+    // Number of distinct points probed so far
+    pub fn probed(&self) -> usize {
+        self.cache.len()
+    }
+    // Number of points asked about so far, cache hits included
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+    fn walk_left(&mut self, mut x: isize, y: isize, min: isize) -> isize {
+        while x > min && self.is_pulled(x - 1, y) {
+            x -= 1;
+        }
+        x
+    }
+    fn walk_right(&mut self, mut x: isize, y: isize) -> isize {
+        while self.is_pulled(x + 1, y) {
+            x += 1;
+        }
+        x
+    }
+    // Finds the first row below the origin with any beam on it. Rows above
+    // it are empty, since the beam only widens further out.
+    fn seed(&mut self) {
+        self.seeded = true;
+        self.rows = vec![if self.is_pulled(0, 0) { Some((0, 0)) } else { None }];
+        let mut size = 8;
+        while size <= MAX_SEED {
+            let hit = (1..size)
+                .flat_map(|y| (0..size).map(move |x| (x, y)))
+                .find(|&(x, y)| self.is_pulled(x, y));
+            if let Some((x, y)) = hit {
+                self.rows.resize(y as usize, None);
+                let left = self.walk_left(x, y, 0);
+                let right = self.walk_right(x, y);
+                self.rows.push(Some((left, right)));
+                self.last = Some((y, left, right));
+                return;
+            }
+            size *= 2;
+        }
+    }
+    // Uses the edges of the last row with any beam, scaled to this row, to
+    // guess where to look, so each row costs a handful of probes however
+    // steep or shallow the beam is.
+    fn trace_next_row(&mut self) -> Option<(isize, isize)> {
+        let y = self.rows.len() as isize;
+        let (ly, l, r) = self.last?;
+        let guess = (l * y / ly).max(l);
+        let hi = (r + 1) * y / ly + 1;
+        let inside = if self.is_pulled(guess, y) {
+            Some(guess)
+        } else {
+            // A narrow row may sit just left of the guess
+            let near = ((guess - 2).max(l)..guess).rev().find(|&x| self.is_pulled(x, y));
+            near.or_else(|| (guess + 1..=hi).find(|&x| self.is_pulled(x, y)))
+        };
+        let extent = inside.map(|x| {
+            let left = self.walk_left(x, y, l);
+            let mut right = (r * y / ly).max(x);
+            if self.is_pulled(right, y) {
+                right = self.walk_right(right, y);
+            } else {
+                while !self.is_pulled(right, y) {
+                    right -= 1;
+                }
+            }
+            (left, right)
+        });
+        if let Some((left, right)) = extent {
+            self.last = Some((y, left, right));
+        }
+        extent
+    }
+    // Inclusive columns covered by the beam on row `y`
+    pub fn row_extent(&mut self, y: isize) -> Option<(isize, isize)> {
+        if y < 0 {
+            return None;
+        }
+        if !self.seeded {
+            self.seed();
+        }
+        while self.rows.len() as isize <= y {
+            let extent = self.trace_next_row();
+            self.rows.push(extent);
+        }
+        self.rows[y as usize]
+    }
+    // Simplest slopes consistent with every row traced so far, or None if
+    // the rows do not line up with straight edges through the origin
+    pub fn edge_slopes(&self) -> Option<EdgeSlopes> {
+        let traced = self.rows.iter().enumerate().skip(1)
+            .filter_map(|(y, e)| e.map(|(l, r)| (y as isize, l, r)));
+        // Edge x = ceil(a * y) puts a in ((l - 1) / y, l / y], and
+        // x = floor(b * y) puts b in [r / y, (r + 1) / y)
+        let zero = Ratio{ num: 0, den: 1 };
+        let unbounded = Ratio{ num: 1, den: 0 };
+        let (mut a_lo, mut a_hi) = (zero, unbounded);
+        let (mut b_lo, mut b_hi) = (zero, unbounded);
+        let mut any = false;
+        for (y, l, r) in traced {
+            any = true;
+            a_lo = a_lo.max(Ratio::new(l - 1, y));
+            a_hi = a_hi.min(Ratio::new(l, y));
+            b_lo = b_lo.max(Ratio::new(r, y));
+            b_hi = b_hi.min(Ratio::new(r + 1, y));
+        }
+        if !any {
+            return None;
+        }
+        Some(EdgeSlopes{
+            left: simplest_between(a_lo, false, a_hi, true)?,
+            right: simplest_between(b_lo, true, b_hi, false)?,
+        })
+    }
+    // Top-left corner of the w x h rectangle closest to the origin that
+    // fits entirely inside the beam. Looks no further than row `max_y`.
+    pub fn first_fit(&mut self, w: isize, h: isize, max_y: isize) -> Option<(isize, isize)> {
+        assert!(w > 0 && h > 0, "precondition failed, empty rectangle");
+        // Rows only shift right, so a rectangle fits when its bottom-left
+        // and top-right corners do
+        for bottom in (h - 1)..=max_y {
+            let top = bottom - h + 1;
+            if let (Some((x, _)), Some((_, top_right))) = (self.row_extent(bottom), self.row_extent(top)) {
+                if top_right >= x + w - 1 {
+                    return Some((x, top));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simplest_between, BeamModel, EdgeSlopes, Ratio};
+    use std::cell::Cell;
+
+    // Cone with left * y <= x <= right * y, slopes given as num/den
+    fn cone(left: (isize, isize), right: (isize, isize)) -> impl Fn(isize, isize) -> bool {
+        move |x, y| x * left.1 >= y * left.0 && x * right.1 <= y * right.0
+    }
+
+    fn brute_force_fit(beam: &dyn Fn(isize, isize) -> bool, w: isize, h: isize, max_y: isize)
+            -> Option<(isize, isize)> {
+        (0..=max_y).flat_map(|y| (0..=4 * max_y).map(move |x| (x, y)))
+            .find(|&(x, y)| (0..h).all(|dy| (0..w).all(|dx| beam(x + dx, y + dy))))
+    }
+
+    #[test]
+    fn simplest_fractions() {
+        let r = Ratio::new;
+        assert_eq!(Some(r(1, 2)), simplest_between(r(2, 5), false, r(3, 5), false));
+        assert_eq!(Some(r(3, 5)), simplest_between(r(4, 7), false, r(3, 5), true));
+        assert_eq!(Some(r(2, 1)), simplest_between(r(7, 4), false, r(9, 4), false));
+        assert_eq!(None, simplest_between(r(1, 2), false, r(1, 2), true));
+        assert_eq!("3/4", r(6, 8).to_string());
+    }
+
+    #[test]
+    fn traces_steep_and_shallow_beams() {
+        for &(left, right) in [((1, 2), (3, 4)), ((2, 1), (3, 1)), ((5, 7), (9, 7))].iter() {
+            let beam = cone(left, right);
+            let mut model = BeamModel::new(&beam);
+            for y in 0..200 {
+                let expected = (0..=4 * y).filter(|&x| beam(x, y)).collect::<Vec<_>>();
+                let extent = expected.first().map(|l| (*l, *expected.last().unwrap()));
+                assert_eq!(extent, model.row_extent(y), "row {} of {:?}", y, (left, right));
+            }
+            assert_eq!(Some(EdgeSlopes{
+                left: Ratio::new(left.0, left.1),
+                right: Ratio::new(right.0, right.1),
+            }), model.edge_slopes());
+        }
+    }
+
+    #[test]
+    fn fits_rectangles() {
+        for &(left, right) in [((1, 2), (3, 4)), ((2, 1), (3, 1))].iter() {
+            let beam = cone(left, right);
+            let mut model = BeamModel::new(&beam);
+            for &(w, h) in [(1, 1), (3, 3), (5, 2), (2, 6)].iter() {
+                assert_eq!(brute_force_fit(&beam, w, h, 60), model.first_fit(w, h, 60),
+                    "{}x{} in {:?}", w, h, (left, right));
+            }
+        }
+    }
+
+    #[test]
+    fn probes_are_cached() {
+        let calls = Cell::new(0);
+        let beam = cone((1, 2), (3, 4));
+        let mut model = BeamModel::new(|x, y| {
+            calls.set(calls.get() + 1);
+            beam(x, y)
+        });
+        model.row_extent(500);
+        let traced = calls.get();
+        assert!(traced < 8 * 8 + 6 * 500, "{} probes to trace 500 rows", traced);
+        assert_eq!(Some((200, 300)), model.row_extent(400));
+        assert!(model.is_pulled(250, 500));
+        assert!(!model.is_pulled(-1, 3));
+        assert_eq!(traced, calls.get());
+        assert_eq!(traced, model.probed());
+        assert!(model.queries() > model.probed());
+    }
+}
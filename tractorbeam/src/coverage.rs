@@ -0,0 +1,153 @@
+use gridmap::{Grid, Point};
+
+use crate::beam::{BeamModel, Probe};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Region {
+    pub x: isize,
+    pub y: isize,
+    pub w: usize,
+    pub h: usize,
+}
+impl Region {
+    pub fn new(x: isize, y: isize, w: usize, h: usize) -> Self {
+        Region{ x, y, w, h }
+    }
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= self.x && y >= self.y
+            && x < self.x + self.w as isize && y < self.y + self.h as isize
+    }
+}
+
+// Which cells of a region the beam covers, plus what it cost to find out.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct CoverageMap {
+    region: Region,
+    cells: Grid<bool>,
+    probes: usize,
+    queries: usize,
+}
+impl CoverageMap {
+    fn build<P, F>(model: &mut BeamModel<P>, region: Region, mut fill_row: F) -> Self
+            where P: Probe, F: FnMut(&mut BeamModel<P>, isize, &mut [bool]) {
+        let (probes, queries) = (model.probed(), model.queries());
+        let mut cells = Grid::new(region.w, region.h, false);
+        for row in 0..region.h {
+            fill_row(model, region.y + row as isize, &mut cells[row]);
+        }
+        CoverageMap{
+            region,
+            cells,
+            probes: model.probed() - probes,
+            queries: model.queries() - queries,
+        }
+    }
+    // Asks about every cell in the region
+    pub fn probe_all<P: Probe>(model: &mut BeamModel<P>, region: Region) -> Self {
+        CoverageMap::build(model, region, |model, y, row| {
+            for (i, cell) in row.iter_mut().enumerate() {
+                *cell = model.is_pulled(region.x + i as isize, y);
+            }
+        })
+    }
+    // Fills rows from the model's traced beam edges, which takes a few
+    // probes per row instead of one per cell
+    pub fn from_edges<P: Probe>(model: &mut BeamModel<P>, region: Region) -> Self {
+        CoverageMap::build(model, region, |model, y, row| {
+            if let Some((left, right)) = model.row_extent(y) {
+                for (i, cell) in row.iter_mut().enumerate() {
+                    let x = region.x + i as isize;
+                    *cell = x >= left && x <= right;
+                }
+            }
+        })
+    }
+    pub fn region(&self) -> Region {
+        self.region
+    }
+    pub fn cells(&self) -> &Grid<bool> {
+        &self.cells
+    }
+    // Points outside the region count as not covered
+    pub fn is_pulled(&self, x: isize, y: isize) -> bool {
+        self.region.contains(x, y)
+            && self.cells[Point::new((x - self.region.x) as i32, (y - self.region.y) as i32)]
+    }
+    // Points newly probed while building the map
+    pub fn probes(&self) -> usize {
+        self.probes
+    }
+    // Points asked about while building the map, cached or not
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+    pub fn area(&self) -> usize {
+        self.cells.iter().filter(|(_, c)| **c).count()
+    }
+    // First and last covered column of each row in the region
+    pub fn row_extents(&self) -> Vec<Option<(isize, isize)>> {
+        self.cells.rows().take(self.region.h)
+            .map(|row| {
+                let first = row.iter().position(|c| *c)?;
+                let last = row.iter().rposition(|c| *c)?;
+                Some((self.region.x + first as isize, self.region.x + last as isize))
+            })
+            .collect()
+    }
+    // Directions of the beam's two edges, in degrees from the x axis
+    // towards the y axis, judged from the row furthest out whose extent is
+    // not cut short by the sides of the region
+    pub fn edge_angles(&self) -> Option<(f64, f64)> {
+        let last_x = self.region.x + self.region.w as isize - 1;
+        let (dy, (left, right)) = self.row_extents().into_iter().enumerate().rev()
+            .filter_map(|(dy, e)| e.map(|e| (dy, e)))
+            .find(|(_, (left, right))| *left > self.region.x && *right < last_x)?;
+        let y = (self.region.y + dy as isize) as f64;
+        Some((y.atan2(left as f64).to_degrees(), y.atan2(right as f64).to_degrees()))
+    }
+    // Angle between the edges
+    pub fn beam_angle(&self) -> Option<f64> {
+        self.edge_angles().map(|(left, right)| left - right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CoverageMap, Region};
+    use crate::beam::BeamModel;
+
+    // Covers y / 2 <= x <= y
+    fn wedge(x: isize, y: isize) -> bool {
+        2 * x >= y && x <= y
+    }
+
+    #[test]
+    fn edges_match_exhaustive_scan() {
+        let region = Region::new(3, 2, 12, 9);
+        let all = CoverageMap::probe_all(&mut BeamModel::new(wedge), region);
+        let mut model = BeamModel::new(wedge);
+        let traced = CoverageMap::from_edges(&mut model, region);
+        assert_eq!(all.cells(), traced.cells());
+        assert_eq!(30, all.area());
+        assert_eq!(12 * 9, all.probes());
+        assert!(traced.probes() < all.probes());
+        // Already traced rows cost nothing the second time round
+        assert_eq!(0, CoverageMap::from_edges(&mut model, region).probes());
+    }
+
+    #[test]
+    fn statistics() {
+        let map = CoverageMap::probe_all(&mut BeamModel::new(wedge), Region::new(0, 0, 5, 5));
+        assert_eq!(vec![Some((0, 0)), Some((1, 1)), Some((1, 2)), Some((2, 3)), Some((2, 4))],
+            map.row_extents());
+        assert!(map.is_pulled(3, 4));
+        assert!(!map.is_pulled(5, 5));
+        // Row 4 runs into the right side, so row 3 is the one measured
+        let (left, right) = map.edge_angles().unwrap();
+        assert!((left - 56.31).abs() < 0.01);
+        assert!((right - 45.0).abs() < 0.01);
+        assert!((map.beam_angle().unwrap() - 11.31).abs() < 0.01);
+        assert_eq!(None, CoverageMap::probe_all(&mut BeamModel::new(wedge), Region::new(0, 1, 1, 1))
+            .beam_angle());
+    }
+}
@@ -1,7 +1,13 @@
 mod intcode;
 use intcode::{Processor, Resources};
 
-struct Beam<'a> {
+mod beam;
+pub use beam::{BeamModel, EdgeSlopes, Probe, Ratio};
+
+mod coverage;
+pub use coverage::{CoverageMap, Region};
+
+pub struct Beam<'a> {
     mem: &'a Vec<isize>,
     proc: Processor,
 }
@@ -23,44 +29,23 @@ impl<'a> Beam<'a> {
         }
     }
 }
-
-pub fn scan_immediate_area(mem: &Vec<isize>) -> u64 {
-    let mut i = 0;
-    let beam = Beam::new(mem);
-    for y in 0..50 {
-        for x in 0..50 {
-            if beam.check(x, y) {
-                i += 1;
-                print!("#");
-            } else {
-                print!(".");
-            }
-        }
-        println!("");
+impl<'a> Probe for Beam<'a> {
+    fn probe(&mut self, x: isize, y: isize) -> bool {
+        self.check(x, y)
     }
-    i as u64
 }
 
-fn upper_bound_next_point(beam: &Beam, x: isize, y: isize) -> (isize, isize) {
-    let (mut nx, mut ny) = (x, y);
-    nx += 1;
-    while !beam.check(nx, ny) { ny += 1; }
-    println!("beam top edge: ({}, {})", nx, ny);
-    (nx, ny)
+pub fn scan_immediate_area(mem: &Vec<isize>) -> CoverageMap {
+    CoverageMap::probe_all(&mut BeamModel::new(Beam::new(mem)), Region::new(0, 0, 50, 50))
 }
 
+// Rows searched before concluding the ship cannot fit
+const MAX_SHIP_ROW: isize = 100_000;
+
 pub fn scan_for_ship_size(mem: &Vec<isize>) -> (isize, isize) {
     const N: isize = 100;
-    let (mut x, mut y) = (6, 0);
-    let beam = Beam::new(mem);
-    loop {
-        // Observed beam is above y = x line, so we just check upper bound
-        let (tmpx, tmpy) = upper_bound_next_point(&beam, x, y);
-        x = tmpx; y = tmpy;
-        if beam.check(x - N + 1, y) && beam.check(x - N + 1, y + N - 1) {
-            return (x - N + 1, y)
-        }
-    }
+    let mut model = BeamModel::new(Beam::new(mem));
+    model.first_fit(N, N, MAX_SHIP_ROW).expect("ship does not fit in the beam")
 }
 
 #[cfg(test)]
@@ -1,7 +1,7 @@
 use std::io;
 use std::fs::read_to_string;
 
-use tractorbeam::{scan_immediate_area, scan_for_ship_size};
+use tractorbeam::{scan_immediate_area, scan_for_ship_size, Beam, BeamModel, CoverageMap};
 
 fn main() -> io::Result<()> {
     let input = read_to_string("input.txt")?;
@@ -10,8 +10,15 @@ fn main() -> io::Result<()> {
         .collect();
 
     // Part 1
-    let num_influenced_squares = scan_immediate_area(&mem);
-    println!("num influenced squares: {}", num_influenced_squares);
+    let area = scan_immediate_area(&mem);
+    for row in area.cells().rows() {
+        println!("{}", row.iter().map(|c| if *c { '#' } else { '.' }).collect::<String>());
+    }
+    println!("num influenced squares: {}", area.area());
+    println!("beam angle: {:.2} degrees, {} probes", area.beam_angle().unwrap_or(0.0), area.probes());
+    let mut model = BeamModel::new(Beam::new(&mem));
+    let traced = CoverageMap::from_edges(&mut model, area.region());
+    println!("same area from traced edges: {}, {} probes", traced.area(), traced.probes());
 
     // Part 2
     let coords = scan_for_ship_size(&mem);
@@ -1,9 +1,12 @@
 use std::cmp::min;
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::BufRead;
 use std::fmt;
 use std::rc::Rc;
 
+use graphsearch::bfs;
+use gridmap::{Grid, Point};
+
 struct CursorIter {
     cur_cursor: usize,
     num_cursors: usize,
@@ -27,7 +30,7 @@ impl Iterator for CursorIter {
 
 #[derive(Debug)]
 struct Maze {
-    grid: Vec<Vec<char>>,
+    grid: Grid<char>,
     symbols: HashMap<char, (usize, usize)>,
     distance_travelled: u64,
     keys_collected: HashSet<char>,
@@ -38,24 +41,20 @@ struct Maze {
 impl Maze {
     pub fn from_data<T: BufRead>(data: T) -> Self {
         let mut num_cursors = 0;
-        let mut symbols = HashMap::new();
-        let mut grid = Vec::new();
-        for (y, line) in data.lines().enumerate() {
-            let mut row = Vec::new();
-            for (x, c) in line.expect("line read failed").trim().chars().enumerate() {
-                let ch = if c == '@' {
-                    num_cursors += 1;
-                    ('0' as u8 + num_cursors as u8) as char
-                } else {
-                    c
-                };
-                row.push(ch);
-                if ch != '#' && ch != '.' {
-                    symbols.insert(ch, (x, y));
-                }
-            }
-            grid.push(row);
-        }
+        // Lines are trimmed, so indented or CRLF mazes still line up
+        let text: String = data.lines()
+            .map(|l| l.expect("line read failed").trim().to_string() + "\n")
+            .collect();
+        let grid = Grid::parse(text.as_bytes(), |c| if c == '@' {
+            num_cursors += 1;
+            Some((b'0' + num_cursors as u8) as char)
+        } else {
+            Some(c)
+        }).expect("invalid maze");
+        let symbols = grid.iter()
+            .filter(|(_, &ch)| ch != '#' && ch != '.')
+            .map(|(p, &ch)| (ch, (p.x as usize, p.y as usize)))
+            .collect();
         let lookup = Self::build_distance_lookup(&grid, &symbols);
         let prereqs = Self::build_prereqs(&grid, &symbols, num_cursors);
         let mut maze = Maze{
@@ -76,52 +75,37 @@ impl Maze {
         let mut changed = true;
         while changed {
             changed = false;
-            for y in 1..self.grid.len()-1 {
-                for x in 1..self.grid[0].len()-1 {
-                    if self.grid[y][x] == '.' || self.grid[y][x].is_ascii_uppercase() {
-                        let mut blocked_sides = 0;
-                        if self.grid[y-1][x] == '#' { blocked_sides += 1; }
-                        if self.grid[y+1][x] == '#' { blocked_sides += 1; }
-                        if self.grid[y][x-1] == '#' { blocked_sides += 1; }
-                        if self.grid[y][x+1] == '#' { blocked_sides += 1; }
-                        if blocked_sides >= 3 {
-                            self.grid[y][x] = '#';
-                            changed = true;
-                        }
-                    }
+            let candidates: Vec<Point> = self.grid.iter()
+                .filter(|(_, &c)| c == '.' || c.is_ascii_uppercase())
+                .map(|(p, _)| p)
+                .collect();
+            for p in candidates {
+                let blocked_sides = p.neighbors4().iter()
+                    .filter(|n| self.grid.get(**n).is_none_or(|c| *c == '#'))
+                    .count();
+                if blocked_sides >= 3 {
+                    self.grid[p] = '#';
+                    changed = true;
                 }
             }
         }
     }
-    fn distance(grid: &Vec<Vec<char>>, loc1: &(usize, usize), loc2: &(usize, usize)) -> (Option<(u64, HashSet<char>)>) {
-        let (x1, y1) = *loc1;
-        let (x2, y2) = *loc2;
-        let mut grid = grid.clone();
-        let mut q = VecDeque::new();
-        q.push_back((x1, y1, 0, Rc::new(HashSet::new())));
-        while !q.is_empty() {
-            let (x, y, d, seen) = q.pop_front().unwrap();
-            if x == x2 && y == y2 {
-                return Some((d, (*seen).clone()));
-            } else if grid[y][x] != '#' {
-                let new_seen = if grid[y][x].is_ascii_alphabetic() {
-                    let mut ns = (*seen).clone();
-                    ns.insert(grid[y][x].to_ascii_lowercase());
-                    Rc::new(ns)
-                } else {
-                    seen
-                };
-                grid[y][x] = '#';
-                q.push_back((x-1, y, d+1, new_seen.clone()));
-                q.push_back((x+1, y, d+1, new_seen.clone()));
-                q.push_back((x, y-1, d+1, new_seen.clone()));
-                q.push_back((x, y+1, d+1, new_seen.clone()));
-            }
-        }
-        None
+    fn distance(grid: &Grid<char>, loc1: &(usize, usize), loc2: &(usize, usize)) -> Option<(u64, HashSet<char>)> {
+        let dest = Point::from(*loc2);
+        let open = |p: &Point| grid.neighbors4(*p)
+            .filter(|n| *n == dest || grid[*n] != '#')
+            .collect::<Vec<_>>();
+        let path = bfs(Point::from(*loc1), open, |p| *p == dest)?;
+        // Keys and doors passed on the way, not counting the destination
+        let seen = path.states[..path.len()].iter()
+            .map(|p| grid[*p])
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        Some((path.cost, seen))
     }
     fn build_distance_lookup(
-        grid: &Vec<Vec<char>>, symbols: &HashMap<char, (usize, usize)>
+        grid: &Grid<char>, symbols: &HashMap<char, (usize, usize)>
     ) -> HashMap<(usize, usize, usize, usize), u64> {
         let mut lookup = HashMap::new();
         for &(x1, y1) in symbols.values() {
@@ -134,7 +118,7 @@ impl Maze {
         lookup
     }
     fn build_prereqs(
-        grid: &Vec<Vec<char>>, symbols: &HashMap<char, (usize, usize)>,
+        grid: &Grid<char>, symbols: &HashMap<char, (usize, usize)>,
         num_cursors: usize,
     ) -> HashMap<char, HashSet<char>> {
         let mut prereqs = HashMap::new();
@@ -223,13 +207,7 @@ impl Maze {
 }
 impl fmt::Display for Maze {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for v in self.grid.iter() {
-            for ch in v.iter() {
-                write!(f, "{}", ch)?
-            }
-            write!(f, "\n")?
-        }
-        write!(f, "\n")
+        writeln!(f, "{}", self.grid)
     }
 }
 
@@ -328,12 +306,16 @@ mod tests {
         let m = Maze::from_data(EXAMPLE1);
         assert_eq!((1, 1), m.get_loc_for_symbol('b'));
         assert_eq!((3, 1), m.get_loc_for_symbol('A'));
-        assert_eq!((5, 1), m.get_loc_for_symbol('@'));
+        assert_eq!((5, 1), m.get_loc_for_symbol('1'));
         assert_eq!((7, 1), m.get_loc_for_symbol('a'));
 
         let keys = m.get_keys();
         assert!(keys.contains(&'a'));
         assert!(keys.contains(&'b'));
+
+        // Indented CRLF lines parse the same
+        let m = Maze::from_data(&b"  #########\r\n  #b.A.@.a#\r\n  #########\r\n"[..]);
+        assert_eq!((5, 1), m.get_loc_for_symbol('1'));
     }
 
     #[test]
@@ -342,13 +324,13 @@ mod tests {
 
         let m1 = Maze::from_data(EXAMPLE1);
         assert_eq!(2, m1.num_keys());
-        assert_eq!(Some(2), m1.distance_to_key('a'));
-        assert_eq!(None, m1.distance_to_key('b'));
+        assert_eq!(Some(2), m1.distance_to_key('1', 'a'));
+        assert_eq!(None, m1.distance_to_key('1', 'b'));
 
         let m2 = Maze::from_data(EXAMPLE2);
         assert_eq!(6, m2.num_keys());
-        assert_eq!(Some(2), m2.distance_to_key('a'));
-        assert_eq!(None, m2.distance_to_key('b'));
+        assert_eq!(Some(2), m2.distance_to_key('1', 'a'));
+        assert_eq!(None, m2.distance_to_key('1', 'b'));
     }
 
     #[test]